@@ -0,0 +1,196 @@
+// batched transforms of many equal length signals sharing one plan
+
+use num_complex::Complex;
+use num_traits::Zero;
+use super::Real;
+use real::RealFftPlan;
+use transform::FftPlan;
+
+// how count signals of len points share one buffer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layout {
+    // signals one after another, point i of signal j at j * len + i
+    Planar,
+    // points of all signals alternate, point i of signal j at i * count + j,
+    // eg. frames of a multichannel capture
+    Interleaved,
+}
+
+impl Layout {
+    // buffer index of point i of signal j
+    pub fn index(&self, i: usize, j: usize, len: usize, count: usize) -> usize {
+        match *self {
+            Layout::Planar => j * len + i,
+            Layout::Interleaved => i * count + j,
+        }
+    }
+}
+
+impl<T: Real> FftPlan<T> {
+    // scratch length the batched _with_scratch transforms need,
+    // interleaved signals are gathered into a contiguous buffer first
+    pub fn batch_scratch_len(&self, layout: Layout) -> usize {
+        match layout {
+            Layout::Planar => self.scratch_len(),
+            Layout::Interleaved => self.len() + self.scratch_len(),
+        }
+    }
+
+    // in-place forward transform of count signals stored in layout
+    pub fn process_batch(&self, data: &mut [Complex<T>], count: usize, layout: Layout) {
+        let mut scratch = vec![Complex::zero(); self.batch_scratch_len(layout)];
+        self.process_batch_with_scratch(data, count, layout, &mut scratch);
+    }
+
+    // in-place inverse transform of count signals, each scaled by 1/N
+    pub fn inverse_batch(&self, data: &mut [Complex<T>], count: usize, layout: Layout) {
+        let mut scratch = vec![Complex::zero(); self.batch_scratch_len(layout)];
+        self.inverse_batch_with_scratch(data, count, layout, &mut scratch);
+    }
+
+    // batched forward transform that never allocates
+    pub fn process_batch_with_scratch(&self, data: &mut [Complex<T>], count: usize, layout: Layout, scratch: &mut [Complex<T>]) {
+        self.batch(data, count, layout, scratch, false)
+    }
+
+    // batched inverse transform that never allocates
+    pub fn inverse_batch_with_scratch(&self, data: &mut [Complex<T>], count: usize, layout: Layout, scratch: &mut [Complex<T>]) {
+        self.batch(data, count, layout, scratch, true)
+    }
+
+    fn batch(&self, data: &mut [Complex<T>], count: usize, layout: Layout, scratch: &mut [Complex<T>], inverse: bool) {
+        let len = self.len();
+        assert_eq!(data.len(), len * count, "{} signals of {} points need {} points, got {}",
+                   count, len, len * count, data.len());
+        assert!(scratch.len() >= self.batch_scratch_len(layout), "scratch of {} points, batch needs {}",
+                scratch.len(), self.batch_scratch_len(layout));
+        if len == 0 {
+            return;
+        }
+
+        let transform = |signal: &mut [Complex<T>], scratch: &mut [Complex<T>]| {
+            if inverse {
+                self.inverse_with_scratch(signal, scratch);
+            } else {
+                self.process_with_scratch(signal, scratch);
+            }
+        };
+
+        match layout {
+            Layout::Planar => {
+                for signal in data.chunks_mut(len) {
+                    transform(signal, scratch);
+                }
+            }
+            Layout::Interleaved => {
+                let (signal, rest) = scratch.split_at_mut(len);
+                for j in 0..count {
+                    for (i, c) in signal.iter_mut().enumerate() {
+                        *c = data[i * count + j];
+                    }
+                    transform(signal, rest);
+                    for (i, &c) in signal.iter().enumerate() {
+                        data[i * count + j] = c;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: Real> RealFftPlan<T> {
+    // half spectra of count real signals stored in layout,
+    // returned in the same layout with N/2+1 bins per signal
+    pub fn forward_batch(&self, input: &[T], count: usize, layout: Layout) -> Vec<Complex<T>> {
+        let len = self.len();
+        let bins = len / 2 + 1;
+        assert_eq!(input.len(), len * count, "{} signals of {} points need {} points, got {}",
+                   count, len, len * count, input.len());
+        if len == 0 {
+            return vec![];
+        }
+
+        let mut output = vec![Complex::zero(); bins * count];
+        let mut scratch = vec![Complex::zero(); self.scratch_len()];
+        let mut signal = vec![T::zero(); len];
+        let mut spectrum = vec![Complex::zero(); bins];
+        for j in 0..count {
+            for (i, x) in signal.iter_mut().enumerate() {
+                *x = input[layout.index(i, j, len, count)];
+            }
+            self.forward_with_scratch(&signal, &mut spectrum, &mut scratch);
+            for (k, &c) in spectrum.iter().enumerate() {
+                output[layout.index(k, j, bins, count)] = c;
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+use super::*;
+use tests::complex_signal;
+
+// count signals of len points, signal j in its own pattern
+fn signals(len: usize, count: usize) -> Vec<Vec<Complex<f32>>> {
+    (0..count).map(|j| complex_signal(len, j)).collect()
+}
+
+fn pack(signals: &[Vec<Complex<f32>>], layout: Layout) -> Vec<Complex<f32>> {
+    let (len, count) = (signals[0].len(), signals.len());
+    let mut data = vec![Complex::zero(); len * count];
+    for (j, signal) in signals.iter().enumerate() {
+        for (i, &c) in signal.iter().enumerate() {
+            data[layout.index(i, j, len, count)] = c;
+        }
+    }
+    data
+}
+
+#[test]
+    fn test_batch_matches_single(){
+        for &(len, count) in &[(64, 7), (100, 3), (1009, 2)] {
+            let plan = FftPlan::new(len);
+            let input = signals(len, count);
+            let expected: Vec<Vec<Complex<f32>>> = input.iter()
+                .map(|s| {
+                    let mut s = s.clone();
+                    plan.process(&mut s);
+                    s
+                })
+                .collect();
+
+            for &layout in &[Layout::Planar, Layout::Interleaved] {
+                let mut data = pack(&input, layout);
+                plan.process_batch(&mut data, count, layout);
+                assert_eq!(data, pack(&expected, layout), "{:?} {}x{}", layout, count, len);
+
+                plan.inverse_batch(&mut data, count, layout);
+                for (a, b) in data.iter().zip(pack(&input, layout).iter()) {
+                    assert!((a - b).norm() < 1e-4, "{:?} {}x{}", layout, count, len);
+                }
+            }
+        }
+    }
+
+#[test]
+    fn test_real_batch(){
+        let (len, count) = (100, 4);
+        let input: Vec<Vec<f32>> = signals(len, count).iter()
+            .map(|s| s.iter().map(|c| c.re).collect())
+            .collect();
+        let plan = RealFftPlan::new(len);
+        let expected: Vec<Vec<Complex<f32>>> = input.iter().map(|s| plan.forward(s)).collect();
+
+        for &layout in &[Layout::Planar, Layout::Interleaved] {
+            let mut data = vec![0.0; len * count];
+            for (j, signal) in input.iter().enumerate() {
+                for (i, &x) in signal.iter().enumerate() {
+                    data[layout.index(i, j, len, count)] = x;
+                }
+            }
+            assert_eq!(plan.forward_batch(&data, count, layout), pack(&expected, layout));
+        }
+    }
+}
@@ -0,0 +1,264 @@
+// linear convolution and fir filtering
+// short operands convolve directly, long ones through the real fft,
+// and streams are filtered block by block with overlap-save
+
+use num_complex::Complex;
+use num_traits::Zero;
+use super::{Sample, Real};
+use real::RealFftPlan;
+
+// shorter operands than this always convolve directly
+const DIRECT_MAX: usize = 32;
+
+// smallest fft length overlap-save uses
+const MIN_BLOCK: usize = 64;
+
+// whether direct convolution of la by lb points beats the fft, which
+// costs three transforms of the padded length
+fn prefer_direct(la: usize, lb: usize) -> bool {
+    if la.min(lb) <= DIRECT_MAX {
+        return true;
+    }
+    let n = (la + lb - 1).next_power_of_two() as f64;
+    (la * lb) as f64 <= 8.0 * n * n.log2()
+}
+
+// full linear convolution of a and b, a.len() + b.len() - 1 points
+// picks the direct or the fft method by size
+pub fn convolve<T: Real>(a: &[T], b: &[T]) -> Vec<T> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    if prefer_direct(a.len(), b.len()) {
+        convolve_direct(a, b)
+    } else {
+        convolve_fft(a, b)
+    }
+}
+
+// convolution by the definition, O(la lb)
+pub fn convolve_direct<T: Real>(a: &[T], b: &[T]) -> Vec<T> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let mut out = vec![T::zero(); a.len() + b.len() - 1];
+    for (i, &x) in a.iter().enumerate() {
+        for (o, &y) in out[i..].iter_mut().zip(b.iter()) {
+            *o += x * y;
+        }
+    }
+    out
+}
+
+// convolution through the real fft zero padded to a power of two,
+// O(n log n)
+pub fn convolve_fft<T: Real>(a: &[T], b: &[T]) -> Vec<T> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let len = a.len() + b.len() - 1;
+    let plan = RealFftPlan::new(len.next_power_of_two());
+    let padded = |x: &[T]| {
+        let mut data = x.to_vec();
+        data.resize(plan.len(), T::zero());
+        plan.forward(&data)
+    };
+    let product: Vec<Complex<T>> = padded(a).iter().zip(padded(b).iter()).map(|(x, y)| x * y).collect();
+    let mut out = plan.inverse(&product);
+    out.truncate(len);
+    out
+}
+
+// streaming fir filter y[n] = sum h[k] x[n-k] by overlap-save
+// every block transforms the last kernel.len() - 1 inputs together
+// with up to block() new ones, and keeps only the outputs the
+// circular wrap did not alias
+pub struct OverlapSave<T = f32> {
+    plan: RealFftPlan<T>,
+    // transformed zero padded kernel
+    kernel: Vec<Complex<T>>,
+    taps: usize,
+    // last taps - 1 inputs, oldest first
+    history: Vec<T>,
+    buffer: Vec<T>,
+    bins: Vec<Complex<T>>,
+    scratch: Vec<Complex<T>>,
+}
+
+impl<T: Real> OverlapSave<T> {
+    // filter for kernel with an fft about four times its length
+    pub fn new(kernel: &[T]) -> OverlapSave<T> {
+        let len = (4 * kernel.len()).next_power_of_two().max(MIN_BLOCK);
+        OverlapSave::with_fft_len(kernel, len)
+    }
+
+    // filter for kernel transforming len points at a time,
+    // len must be at least kernel.len()
+    pub fn with_fft_len(kernel: &[T], len: usize) -> OverlapSave<T> {
+        assert!(!kernel.is_empty(), "fir kernel must not be empty");
+        assert!(len >= kernel.len(), "fft length {} shorter than kernel of {} taps", len, kernel.len());
+        let plan = RealFftPlan::new(len);
+        let mut padded = kernel.to_vec();
+        padded.resize(len, T::zero());
+        OverlapSave {
+            kernel: plan.forward(&padded),
+            taps: kernel.len(),
+            history: vec![T::zero(); kernel.len() - 1],
+            buffer: vec![T::zero(); len],
+            bins: vec![Complex::zero(); len / 2 + 1],
+            scratch: vec![Complex::zero(); plan.scratch_len()],
+            plan,
+        }
+    }
+
+    pub fn taps(&self) -> usize {
+        self.taps
+    }
+
+    // new inputs filtered per transform
+    pub fn block(&self) -> usize {
+        self.plan.len() - self.taps + 1
+    }
+
+    // filters input into output of the same length, continuing from
+    // the previous call, and never allocates
+    pub fn process(&mut self, input: &[T], output: &mut [T]) {
+        assert_eq!(input.len(), output.len(), "input of {} points, output of {}", input.len(), output.len());
+        let block = self.block();
+        for (x, y) in input.chunks(block).zip(output.chunks_mut(block)) {
+            self.run(Some(x), y);
+        }
+    }
+
+    // the taps - 1 outputs still ringing after the last input, as if
+    // zeros followed, and resets the filter
+    pub fn flush(&mut self, output: &mut [T]) {
+        assert_eq!(output.len(), self.taps - 1, "flush writes {} points, got {}", self.taps - 1, output.len());
+        let block = self.block();
+        for y in output.chunks_mut(block) {
+            self.run(None, y);
+        }
+        self.reset();
+    }
+
+    // one block of y.len() <= block() outputs, input x or zeros
+    fn run(&mut self, x: Option<&[T]>, y: &mut [T]) {
+        let keep = self.taps - 1;
+        let n = y.len();
+        self.buffer[..keep].copy_from_slice(&self.history);
+        match x {
+            Some(x) => self.buffer[keep..keep + n].copy_from_slice(x),
+            None => for b in self.buffer[keep..keep + n].iter_mut() { *b = T::zero() },
+        }
+        for b in self.buffer[keep + n..].iter_mut() {
+            *b = T::zero();
+        }
+
+        // the history moves on before the buffer is overwritten
+        self.history.copy_from_slice(&self.buffer[n..n + keep]);
+
+        self.plan.forward_with_scratch(&self.buffer, &mut self.bins, &mut self.scratch);
+        for (b, k) in self.bins.iter_mut().zip(self.kernel.iter()) {
+            *b *= k;
+        }
+        self.plan.inverse_with_scratch(&self.bins, &mut self.buffer, &mut self.scratch);
+        y.copy_from_slice(&self.buffer[keep..keep + n]);
+    }
+
+    // forgets the previous inputs
+    pub fn reset(&mut self) {
+        for x in self.history.iter_mut() {
+            *x = T::zero();
+        }
+    }
+}
+
+// sample filtered with kernel, including the tail, so the result is
+// kernel.len() - 1 points longer
+// long kernels such as room impulse responses go through overlap-save
+pub fn fir_filter<T: Real>(sample: &Sample<T>, kernel: &[T]) -> Sample<T> {
+    if sample.data.is_empty() || kernel.is_empty() {
+        return Sample{ data: vec![], rate: sample.rate };
+    }
+    if prefer_direct(sample.data.len(), kernel.len()) {
+        return Sample{ data: convolve_direct(&sample.data, kernel), rate: sample.rate };
+    }
+    let mut filter = OverlapSave::new(kernel);
+    let len = sample.data.len();
+    let mut data = vec![T::zero(); len + kernel.len() - 1];
+    filter.process(&sample.data, &mut data[..len]);
+    filter.flush(&mut data[len..]);
+    Sample{ data, rate: sample.rate }
+}
+
+#[cfg(test)]
+mod tests {
+use super::*;
+use tests::{allocations, noise};
+
+fn close(a: &[f32], b: &[f32]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| (x - y).abs() < 1e-3)
+}
+
+#[test]
+    fn test_convolve_methods_agree(){
+        assert_eq!(convolve_direct(&[1.0f32, 2.0, 3.0], &[0.0, 1.0, 0.5]), vec![0.0, 1.0, 2.5, 4.0, 1.5]);
+        assert!(convolve::<f32>(&[], &[1.0]).is_empty());
+
+        for &(la, lb) in &[(1, 7), (33, 40), (100, 257), (1000, 999), (3000, 5)] {
+            let a = noise(la, 1);
+            let b = noise(lb, 2);
+            let direct = convolve_direct(&a, &b);
+            assert!(close(&direct, &convolve_fft(&a, &b)), "{} x {}", la, lb);
+            assert!(close(&direct, &convolve(&a, &b)), "{} x {}", la, lb);
+        }
+        assert!(prefer_direct(3000, 5));
+        assert!(!prefer_direct(1000, 999));
+    }
+
+#[test]
+    fn test_overlap_save_streaming(){
+        let x = noise(5000, 3);
+        let h = noise(300, 4);
+        let expected = convolve_direct(&x, &h);
+
+        // uneven chunks straddling block boundaries
+        let mut filter = OverlapSave::with_fft_len(&h, 512);
+        assert_eq!(filter.block(), 213);
+        let mut y = vec![0.0; x.len() + h.len() - 1];
+        let mut start = 0;
+        for &n in [1, 17, 500, 212, 213, 214, 2000].iter().cycle() {
+            let end = (start + n).min(x.len());
+            filter.process(&x[start..end], &mut y[start..end]);
+            start = end;
+            if start == x.len() {
+                break;
+            }
+        }
+        filter.flush(&mut y[x.len()..]);
+        assert!(close(&y, &expected));
+
+        let sample = Sample{ data: x.clone(), rate: 8000 };
+        let filtered = fir_filter(&sample, &h);
+        assert_eq!(filtered.rate, 8000);
+        assert!(close(&filtered.data, &expected));
+
+        // flushed filters start over
+        let mut head = vec![0.0; 100];
+        filter.process(&x[..100], &mut head);
+        assert!(close(&head, &expected[..100]));
+    }
+
+#[test]
+    fn test_overlap_save_no_allocation(){
+        let x = noise(4096, 5);
+        let h = noise(129, 6);
+        let mut filter = OverlapSave::new(&h);
+        let mut y = vec![0.0; x.len()];
+        let mut tail = vec![0.0; h.len() - 1];
+        assert_eq!(allocations(|| {
+            filter.process(&x, &mut y);
+            filter.flush(&mut tail);
+        }), 0);
+    }
+}
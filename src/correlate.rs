@@ -0,0 +1,193 @@
+// correlation and time-delay estimation between samples
+
+use num_complex::Complex;
+use super::{Sample, Real};
+use convolve;
+use real::RealFftPlan;
+
+// correlation values over a contiguous range of integer lags
+pub struct Correlation<T = f32> {
+    // values[i] belongs to lag min_lag + i
+    pub values: Vec<T>,
+    pub min_lag: isize,
+    pub rate: usize,
+}
+
+impl<T: Real> Correlation<T> {
+    pub fn lag(&self, i: usize) -> isize {
+        self.min_lag + i as isize
+    }
+
+    // lag of values[i], seconds
+    pub fn time(&self, i: usize) -> T {
+        T::from_f64(self.lag(i) as f64 / self.rate as f64)
+    }
+
+    // index of the largest value
+    pub fn peak(&self) -> Option<usize> {
+        self.values.iter()
+            .enumerate()
+            .fold(None, |best: Option<(usize, T)>, (i, &v)| match best {
+                Some((_, w)) if w >= v => best,
+                _ => Some((i, v)),
+            })
+            .map(|(i, _)| i)
+    }
+}
+
+// c[k] = sum a[n + k] b[n] for k in -(b.len() - 1)..a.len(),
+// peaks at a positive lag when a is a delayed copy of b
+pub fn cross_correlation<T: Real>(a: &Sample<T>, b: &Sample<T>) -> Correlation<T> {
+    assert_eq!(a.rate, b.rate, "samplerates {} and {} differ", a.rate, b.rate);
+    let reversed: Vec<T> = b.data.iter().rev().cloned().collect();
+    Correlation {
+        values: convolve::convolve(&a.data, &reversed),
+        min_lag: 1 - b.data.len() as isize,
+        rate: a.rate,
+    }
+}
+
+// r[k] = sum x[n + k] x[n] for lags 0..len, r[0] is the energy
+pub fn autocorrelation<T: Real>(sample: &Sample<T>) -> Correlation<T> {
+    let full = cross_correlation(sample, sample);
+    let zero = sample.data.len().saturating_sub(1);
+    Correlation {
+        values: full.values[zero.min(full.values.len())..].to_vec(),
+        min_lag: 0,
+        rate: sample.rate,
+    }
+}
+
+// time delay estimate between two recordings of one source
+pub struct Delay<T = f32> {
+    // delay of a relative to b, seconds, positive when a lags
+    pub lag: T,
+    // the same in samples, interpolated between them
+    pub samples: T,
+    // height of the phase transform peak, 1.0 for a pure delay and
+    // near 0.0 when the recordings are unrelated
+    pub peak: T,
+}
+
+// generalized cross-correlation with phase transform (gcc-phat)
+// the cross spectrum is whitened to unit magnitude so only its phase,
+// which a delay turns into a linear ramp, shapes the correlation,
+// giving a sharp peak even for narrowband or reverberant signals
+// max_lag limits the search to delays up to that many seconds
+pub fn gcc_phat<T: Real>(a: &Sample<T>, b: &Sample<T>, max_lag: Option<T>) -> Delay<T> {
+    assert_eq!(a.rate, b.rate, "samplerates {} and {} differ", a.rate, b.rate);
+    let (la, lb) = (a.data.len(), b.data.len());
+    if la == 0 || lb == 0 {
+        return Delay{ lag: T::zero(), samples: T::zero(), peak: T::zero() };
+    }
+
+    // padded so the circular correlation does not wrap
+    let len = (la + lb - 1).next_power_of_two();
+    let plan = RealFftPlan::new(len);
+    let spectrum = |x: &[T]| {
+        let mut data = x.to_vec();
+        data.resize(len, T::zero());
+        plan.forward(&data)
+    };
+    let whitened: Vec<Complex<T>> = spectrum(&a.data).iter()
+        .zip(spectrum(&b.data).iter())
+        .map(|(x, y)| {
+            let c = x * y.conj();
+            let norm = c.norm();
+            if norm > T::from_f64(1e-20) { c / norm } else { Complex{ re: T::zero(), im: T::zero() } }
+        })
+        .collect();
+    // a pure delay of d leaves e^(-2πikd/len) on every bin, which
+    // the 1/len scaled inverse turns into a unit impulse at d
+    let r = plan.inverse(&whitened);
+
+    // lags -(lb - 1)..la, negative ones wrapped to the end
+    let limit = max_lag.map(|t| (t.as_f64() * a.rate as f64).floor().max(0.0) as isize).unwrap_or(isize::MAX);
+    let low = -((lb as isize - 1).min(limit));
+    let high = (la as isize - 1).min(limit);
+    let at = |k: isize| r[((k + len as isize) % len as isize) as usize].as_f64();
+    let best = (low..=high).fold(low, |best, k| if at(k) > at(best) { k } else { best });
+
+    // parabola through the peak and its neighbours
+    let (left, center, right) = (at(best - 1), at(best), at(best + 1));
+    let curvature = left - 2.0 * center + right;
+    let offset = if best > low && best < high && curvature < 0.0 {
+        (0.5 * (left - right) / curvature).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    let samples = best as f64 + offset;
+    Delay {
+        lag: T::from_f64(samples / a.rate as f64),
+        samples: T::from_f64(samples),
+        peak: T::from_f64(center),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+use super::*;
+use tests::noise;
+
+// x delayed by a fractional number of samples, circularly, by a
+// linear phase ramp over its spectrum
+fn delayed(x: &[f64], delay: f64) -> Vec<f64> {
+    let plan = RealFftPlan::new(x.len());
+    let n = x.len() as f64;
+    let mut bins = plan.forward(x);
+    for (k, c) in bins.iter_mut().enumerate() {
+        *c *= Complex::from_polar(&1.0, &(-2.0 * std::f64::consts::PI * k as f64 * delay / n));
+    }
+    // the nyquist bin has to stay real
+    let last = bins.len() - 1;
+    bins[last].im = 0.0;
+    plan.inverse(&bins)
+}
+
+#[test]
+    fn test_correlation(){
+        let a = Sample{ data: vec![0.0f32, 0.0, 1.0, 2.0, 0.0], rate: 10 };
+        let b = Sample{ data: vec![1.0f32, 2.0], rate: 10 };
+        let c = cross_correlation(&a, &b);
+        assert_eq!(c.min_lag, -1);
+        assert_eq!(c.values, vec![0.0, 0.0, 2.0, 5.0, 2.0, 0.0]);
+        // b shows up in a two samples late
+        let peak = c.peak().unwrap();
+        assert_eq!(c.lag(peak), 2);
+        assert_eq!(c.time(peak), 0.2);
+
+        let r = autocorrelation(&a);
+        assert_eq!(r.values, vec![5.0, 2.0, 0.0, 0.0, 0.0]);
+        assert_eq!(r.peak(), Some(0));
+
+        // long samples go through the fft and agree
+        let x = Sample{ data: noise(3000, 8), rate: 1000 };
+        let mut late = vec![0.0; 125];
+        late.extend_from_slice(&x.data[..2875]);
+        let c = cross_correlation(&Sample{ data: late, rate: 1000 }, &x);
+        assert_eq!(c.time(c.peak().unwrap()), 0.125);
+    }
+
+#[test]
+    fn test_gcc_phat(){
+        let x: Vec<f64> = noise(4096, 9).iter().map(|&v| v as f64).collect();
+        let b = Sample{ data: x.clone(), rate: 48_000 };
+
+        for &d in &[37.0, -12.0, 5.3, -20.7, 0.5] {
+            let a = Sample{ data: delayed(&x, d), rate: 48_000 };
+            let delay = gcc_phat(&a, &b, Some(0.01));
+            assert!((delay.samples - d).abs() < 0.15, "{} for {}", delay.samples, d);
+            assert!((delay.lag - d / 48_000.0).abs() < 0.15 / 48_000.0);
+            assert!(delay.peak > 0.5);
+        }
+
+        // the search window excludes larger delays
+        let a = Sample{ data: delayed(&x, 300.0), rate: 48_000 };
+        assert!((gcc_phat(&a, &b, None).samples - 300.0).abs() < 0.15);
+        assert!(gcc_phat(&a, &b, Some(0.001)).samples.abs() <= 48.0);
+
+        // unrelated recordings give a low peak
+        let other = Sample{ data: noise(4096, 10).iter().map(|&v| v as f64).collect(), rate: 48_000 };
+        assert!(gcc_phat(&other, &b, None).peak < 0.2);
+    }
+}
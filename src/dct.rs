@@ -0,0 +1,415 @@
+// discrete cosine transforms and the mdct, evaluated through the fft
+// unnormalized, as in the usual textbook definitions:
+//   dct-ii   X[k] = sum x[n] cos(π/N (n+½) k)
+//   dct-iii  X[k] = x[0]/2 + sum_{n>0} x[n] cos(π/N n (k+½))
+//   dct-iv   X[k] = sum x[n] cos(π/N (n+½)(k+½))
+// so dct3(dct2(x)) = dct4(dct4(x)) = N/2 x
+
+use std::f64::consts::PI;
+use num_complex::Complex;
+use num_traits::Zero;
+use super::{Sample, Real};
+use transform::{self, FftPlan};
+use window::Window;
+
+// precomputed dct-ii and dct-iii for a fixed length
+// the input is reordered into even samples followed by odd ones
+// reversed, whose length N fft differs from the dct by a phase
+// shift of e^(-iπk/2N) (makhoul)
+pub struct Dct2Plan<T = f32> {
+    len: usize,
+    fft: FftPlan<T>,
+    // e^(-iπk/2N) for k in 0..N
+    shifts: Vec<Complex<T>>,
+}
+
+impl<T: Real> Dct2Plan<T> {
+    pub fn new(len: usize) -> Dct2Plan<T> {
+        let shifts = (0..len).map(|k| transform::cis(-PI * k as f64 / (2 * len) as f64)).collect();
+        Dct2Plan { len, fft: FftPlan::new(len), shifts }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // dct-ii of input
+    pub fn dct2(&self, input: &[T]) -> Vec<T> {
+        let n = self.check(input);
+        let mut v = vec![Complex::zero(); n];
+        for (i, &x) in input.iter().enumerate() {
+            let j = if i % 2 == 0 { i / 2 } else { n - 1 - i / 2 };
+            v[j] = Complex{ re: x, im: T::zero() };
+        }
+        self.fft.process(&mut v);
+        v.iter().zip(self.shifts.iter()).map(|(&c, &w)| (c * w).re).collect()
+    }
+
+    // dct-iii of input, the inverse of dct2 up to a factor N/2
+    pub fn dct3(&self, input: &[T]) -> Vec<T> {
+        let n = self.check(input);
+        if n == 0 {
+            return vec![];
+        }
+        // undo the phase shift, the imaginary part comes from
+        // the mirrored coefficient X[N-k]
+        let mut v: Vec<Complex<T>> = (0..n)
+            .map(|k| {
+                let mirror = if k == 0 { T::zero() } else { input[n - k] };
+                Complex{ re: input[k], im: -mirror } * self.shifts[k].conj()
+            })
+            .collect();
+        self.fft.inverse(&mut v);
+
+        // reorder back and scale to the dct-iii definition
+        let scale = T::from_f64(n as f64 / 2.0);
+        (0..n)
+            .map(|i| {
+                let j = if i % 2 == 0 { i / 2 } else { n - 1 - i / 2 };
+                v[j].re * scale
+            })
+            .collect()
+    }
+
+    fn check(&self, input: &[T]) -> usize {
+        assert_eq!(input.len(), self.len, "plan length {} does not match data length {}",
+                   self.len, input.len());
+        self.len
+    }
+}
+
+// precomputed dct-iv for a fixed length
+// even lengths pack pairs of samples into a complex fft of
+// half the length, odd ones go through a zero padded fft of 2N
+pub struct Dct4Plan<T = f32> {
+    len: usize,
+    fft: FftPlan<T>,
+    // twiddles applied before and after the fft
+    pre: Vec<Complex<T>>,
+    post: Vec<Complex<T>>,
+}
+
+impl<T: Real> Dct4Plan<T> {
+    pub fn new(len: usize) -> Dct4Plan<T> {
+        let n = len as f64;
+        if len.is_multiple_of(2) {
+            let m = len / 2;
+            Dct4Plan {
+                len,
+                fft: FftPlan::new(m),
+                pre: (0..m).map(|j| transform::cis(-PI * (4 * j + 1) as f64 / (4.0 * n))).collect(),
+                post: (0..m).map(|k| transform::cis(-PI * k as f64 / n)).collect(),
+            }
+        } else {
+            Dct4Plan {
+                len,
+                fft: FftPlan::new(2 * len),
+                pre: (0..len).map(|j| transform::cis(-PI * j as f64 / (2.0 * n))).collect(),
+                post: (0..len).map(|k| transform::cis(-PI * (k as f64 + 0.5) / (2.0 * n))).collect(),
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // dct-iv of input, its own inverse up to a factor N/2
+    pub fn dct4(&self, input: &[T]) -> Vec<T> {
+        let n = self.len;
+        assert_eq!(input.len(), n, "plan length {} does not match data length {}", n, input.len());
+        if n == 0 {
+            return vec![];
+        }
+
+        if !n.is_multiple_of(2) {
+            let mut v = vec![Complex::zero(); 2 * n];
+            for (c, (&x, &w)) in v.iter_mut().zip(input.iter().zip(self.pre.iter())) {
+                *c = w * x;
+            }
+            self.fft.process(&mut v);
+            return v.iter().zip(self.post.iter()).map(|(&c, &w)| (c * w).re).collect();
+        }
+
+        // z[j] = x[2j] + i x[N-1-2j]
+        let m = n / 2;
+        let mut z: Vec<Complex<T>> = (0..m)
+            .map(|j| Complex{ re: input[2 * j], im: input[n - 1 - 2 * j] } * self.pre[j])
+            .collect();
+        self.fft.process(&mut z);
+
+        let mut out = vec![T::zero(); n];
+        for (k, (&c, &w)) in z.iter().zip(self.post.iter()).enumerate() {
+            let y = c * w;
+            out[2 * k] = y.re;
+            out[n - 1 - 2 * k] = -y.im;
+        }
+        out
+    }
+}
+
+// dct-ii of data, builds a throwaway plan
+pub fn dct2<T: Real>(data: &[T]) -> Vec<T> {
+    Dct2Plan::new(data.len()).dct2(data)
+}
+
+// dct-iii of data, builds a throwaway plan
+pub fn dct3<T: Real>(data: &[T]) -> Vec<T> {
+    Dct2Plan::new(data.len()).dct3(data)
+}
+
+// dct-iv of data, builds a throwaway plan
+pub fn dct4<T: Real>(data: &[T]) -> Vec<T> {
+    Dct4Plan::new(data.len()).dct4(data)
+}
+
+// windows of 2N points meeting the princen-bradley condition
+// w[n]² + w[n+N]² = 1, which makes the mdct time domain aliasing
+// of overlapping frames cancel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MdctWindow {
+    // sin(π(n+½)/2N)
+    Sine,
+    // sin(π/2 sin²(π(n+½)/2N)), as used in vorbis
+    Vorbis,
+    // kaiser-bessel derived with parameter α, as used in aac
+    KaiserBessel(f32),
+}
+
+impl MdctWindow {
+    // window coefficients for frames of len = 2N points
+    pub fn coefficients<T: Real>(&self, len: usize) -> Vec<T> {
+        let n = len / 2;
+        let half: Vec<f64> = match *self {
+            MdctWindow::Sine => (0..n)
+                .map(|i| (PI * (i as f64 + 0.5) / len as f64).sin())
+                .collect(),
+            MdctWindow::Vorbis => (0..n)
+                .map(|i| {
+                    let s = (PI * (i as f64 + 0.5) / len as f64).sin();
+                    (PI / 2.0 * s * s).sin()
+                })
+                .collect(),
+            MdctWindow::KaiserBessel(alpha) => {
+                // running sums of a kaiser window over N+1 points
+                let kaiser = Window::Kaiser(PI as f32 * alpha);
+                let total: f64 = (0..=n).map(|j| kaiser.value(j, n)).sum();
+                (0..n)
+                    .scan(0.0, |sum, j| {
+                        *sum += kaiser.value(j, n);
+                        Some((*sum / total).sqrt())
+                    })
+                    .collect()
+            }
+        };
+        // symmetric around the frame center
+        half.iter().chain(half.iter().rev()).map(|&w| T::from_f64(w)).collect()
+    }
+}
+
+// precomputed windowed mdct taking frames of 2N points to N coefficients
+// X[k] = sum_{n<2N} w[n] x[n] cos(π/N (n + ½ + N/2)(k + ½))
+// evaluated as a dct-iv of the frame folded to N points
+pub struct MdctPlan<T = f32> {
+    len: usize,
+    window: Vec<T>,
+    dct4: Dct4Plan<T>,
+}
+
+impl<T: Real> MdctPlan<T> {
+    // len is the number of coefficients N, the hop between frames
+    pub fn new(len: usize, window: &MdctWindow) -> MdctPlan<T> {
+        assert!(len > 0 && len.is_multiple_of(2), "mdct length must be even and positive, got {}", len);
+        MdctPlan { len, window: window.coefficients(2 * len), dct4: Dct4Plan::new(len) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // N coefficients of a 2N point frame
+    pub fn forward(&self, frame: &[T]) -> Vec<T> {
+        let (n, h) = (self.len, self.len / 2);
+        assert_eq!(frame.len(), 2 * n, "mdct of {} coefficients takes {} points, got {}",
+                   n, 2 * n, frame.len());
+        let x: Vec<T> = frame.iter().zip(self.window.iter()).map(|(&x, &w)| x * w).collect();
+
+        // frame quarters a, b, c, d fold to (-c_r - d, a - b_r),
+        // _r reversed
+        let folded: Vec<T> = (0..n)
+            .map(|i| {
+                if i < h {
+                    -x[n + h - 1 - i] - x[n + h + i]
+                } else {
+                    let i = i - h;
+                    x[i] - x[n - 1 - i]
+                }
+            })
+            .collect();
+        self.dct4.dct4(&folded)
+    }
+
+    // windowed 2N point frame from N coefficients, scaled by 2/N
+    // overlap-adding frames N points apart cancels the aliasing
+    pub fn inverse(&self, coefficients: &[T]) -> Vec<T> {
+        let (n, h) = (self.len, self.len / 2);
+        assert_eq!(coefficients.len(), n, "expected {} coefficients, got {}", n, coefficients.len());
+        let scale = T::from_f64(2.0 / n as f64);
+        let y: Vec<T> = self.dct4.dct4(coefficients).iter().map(|&v| v * scale).collect();
+
+        // halves y1, y2 unfold to (y2, -y2_r, -y1_r, -y1)
+        (0..2 * n)
+            .map(|i| {
+                let v = match i / h {
+                    0 => y[h + i],
+                    1 => -y[n - 1 - (i - h)],
+                    2 => -y[h - 1 - (i - n)],
+                    _ => -y[i - n - h],
+                };
+                v * self.window[i]
+            })
+            .collect()
+    }
+}
+
+// mdct coefficients of a sample
+pub struct MdctFrames<T = f32> {
+    // frames[i][k]: coefficient k of frame i
+    pub frames: Vec<Vec<T>>,
+    // coefficients per frame, also the hop between frames
+    pub hop: usize,
+    pub window: MdctWindow,
+    // length and samplerate of the analyzed sample
+    pub len: usize,
+    pub rate: usize,
+}
+
+// mdct of sample in frames of 2 * hop points, hop apart
+// the sample is zero padded by hop on both ends so every sample
+// is covered by two frames and reconstructs exactly
+pub fn mdct<T: Real>(sample: &Sample<T>, hop: usize, window: &MdctWindow) -> MdctFrames<T> {
+    let plan = MdctPlan::new(hop, window);
+    let len = sample.data.len();
+    let count = len.div_ceil(hop) + 1;
+
+    let mut padded = vec![T::zero(); (count + 1) * hop];
+    padded[hop..hop + len].copy_from_slice(&sample.data);
+    let frames = (0..count)
+        .map(|i| plan.forward(&padded[i * hop..i * hop + 2 * hop]))
+        .collect();
+
+    MdctFrames { frames, hop, window: *window, len, rate: sample.rate }
+}
+
+// rebuilds a sample from mdct frames by overlap-add
+pub fn imdct<T: Real>(frames: &MdctFrames<T>) -> Sample<T> {
+    let hop = frames.hop;
+    let plan = MdctPlan::new(hop, &frames.window);
+    let mut padded = vec![T::zero(); (frames.frames.len() + 1) * hop];
+    for (i, coefficients) in frames.frames.iter().enumerate() {
+        for (y, x) in padded[i * hop..].iter_mut().zip(plan.inverse(coefficients)) {
+            *y += x;
+        }
+    }
+    let data = padded.into_iter().skip(hop).take(frames.len).collect();
+    Sample{ data, rate: frames.rate }
+}
+
+#[cfg(test)]
+mod tests {
+use super::*;
+use tests::complex_signal;
+use util;
+
+// real part of the shared complex signal with a little of its
+// imaginary part mixed in
+fn signal(n: usize) -> Vec<f64> {
+    complex_signal::<f64>(n, 0).iter().map(|c| c.re + 0.25 * c.im).collect()
+}
+
+#[test]
+    fn test_dct_definitions(){
+        for &n in &[1, 2, 7, 8, 30, 64] {
+            let x = signal(n);
+            let nf = n as f64;
+            let direct = |f: &dyn Fn(usize, usize) -> f64| -> Vec<f64> {
+                (0..n).map(|k| (0..n).map(|i| x[i] * f(i, k)).sum()).collect()
+            };
+            let ii = direct(&|i, k| (PI / nf * (i as f64 + 0.5) * k as f64).cos());
+            let iii = direct(&|i, k| if i == 0 { 0.5 } else { (PI / nf * i as f64 * (k as f64 + 0.5)).cos() });
+            let iv = direct(&|i, k| (PI / nf * (i as f64 + 0.5) * (k as f64 + 0.5)).cos());
+
+            for (actual, expected) in [(dct2(&x), ii), (dct3(&x), iii), (dct4(&x), iv)].iter() {
+                for (a, e) in actual.iter().zip(expected.iter()) {
+                    assert!((a - e).abs() < 1e-9, "n = {}", n);
+                }
+            }
+            // inverses up to N/2
+            for (a, b) in dct3(&dct2(&x)).iter().zip(x.iter()) {
+                assert!((a - b * nf / 2.0).abs() < 1e-9);
+            }
+            for (a, b) in dct4(&dct4(&x)).iter().zip(x.iter()) {
+                assert!((a - b * nf / 2.0).abs() < 1e-9);
+            }
+        }
+    }
+
+#[test]
+    fn test_mdct_definition(){
+        let n = 16;
+        let x = signal(2 * n);
+        let plan = MdctPlan::new(n, &MdctWindow::Sine);
+        let w: Vec<f64> = MdctWindow::Sine.coefficients(2 * n);
+        let basis = |i: usize, k: usize| (PI / n as f64 * (i as f64 + 0.5 + n as f64 / 2.0) * (k as f64 + 0.5)).cos();
+
+        let coefficients = plan.forward(&x);
+        for (k, &c) in coefficients.iter().enumerate() {
+            let expected: f64 = (0..2 * n).map(|i| w[i] * x[i] * basis(i, k)).sum();
+            assert!((c - expected).abs() < 1e-9);
+        }
+        for (i, &y) in plan.inverse(&coefficients).iter().enumerate() {
+            let expected: f64 = (0..n).map(|k| coefficients[k] * basis(i, k)).sum::<f64>() * w[i] * 2.0 / n as f64;
+            assert!((y - expected).abs() < 1e-9);
+        }
+    }
+
+#[test]
+    fn test_mdct_windows(){
+        for window in &[MdctWindow::Sine, MdctWindow::Vorbis, MdctWindow::KaiserBessel(4.0)] {
+            let w: Vec<f64> = window.coefficients(64);
+            for i in 0..32 {
+                assert!((w[i] * w[i] + w[i + 32] * w[i + 32] - 1.0).abs() < 1e-12, "{:?}", window);
+                assert_eq!(w[i], w[63 - i]);
+            }
+        }
+    }
+
+#[test]
+    fn test_mdct_roundtrip(){
+        let sample: Sample = util::sinewaves(1.0, 1000, &[(50.0,0.0),(123.0,45.0)]);
+        for window in &[MdctWindow::Sine, MdctWindow::Vorbis, MdctWindow::KaiserBessel(4.0)] {
+            for &hop in &[64, 100, 256] {
+                let frames = mdct(&sample, hop, window);
+                assert!(frames.frames.iter().all(|f| f.len() == hop));
+                let restored = imdct(&frames);
+                assert_eq!(restored.rate, sample.rate);
+                assert_eq!(restored.data.len(), sample.data.len());
+                for (a, b) in restored.data.iter().zip(sample.data.iter()) {
+                    assert!((a - b).abs() < 1e-4, "{:?} hop {}", window, hop);
+                }
+            }
+        }
+    }
+}
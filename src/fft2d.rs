@@ -0,0 +1,176 @@
+// two dimensional transforms over row-major matrices
+// the 2d dft is separable: transform every row, then every column
+// rows are a planar batch and columns an interleaved one
+
+use num_complex::Complex;
+use num_traits::Zero;
+use super::Real;
+use batch::Layout;
+use transform::FftPlan;
+
+// precomputed transform for rows x cols matrices
+pub struct Fft2Plan<T = f32> {
+    rows: usize,
+    cols: usize,
+    // transforms a row, cols points
+    row_plan: FftPlan<T>,
+    // transforms a column, rows points
+    col_plan: FftPlan<T>,
+}
+
+impl<T: Real> Fft2Plan<T> {
+    pub fn new(rows: usize, cols: usize) -> Fft2Plan<T> {
+        Fft2Plan { rows, cols, row_plan: FftPlan::new(cols), col_plan: FftPlan::new(rows) }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    // scratch length the _with_scratch transforms need
+    pub fn scratch_len(&self) -> usize {
+        self.row_plan.batch_scratch_len(Layout::Planar)
+            .max(self.col_plan.batch_scratch_len(Layout::Interleaved))
+    }
+
+    // in-place forward transform
+    // X[u][v] = sum x[r][c] * e^(-2πi(ur/rows + vc/cols))
+    pub fn process(&self, data: &mut [Complex<T>]) {
+        let mut scratch = vec![Complex::zero(); self.scratch_len()];
+        self.process_with_scratch(data, &mut scratch);
+    }
+
+    // in-place inverse transform, scaled by 1/(rows*cols)
+    pub fn inverse(&self, data: &mut [Complex<T>]) {
+        let mut scratch = vec![Complex::zero(); self.scratch_len()];
+        self.inverse_with_scratch(data, &mut scratch);
+    }
+
+    // forward transform that never allocates
+    pub fn process_with_scratch(&self, data: &mut [Complex<T>], scratch: &mut [Complex<T>]) {
+        self.check(data);
+        if data.is_empty() {
+            return;
+        }
+        self.row_plan.process_batch_with_scratch(data, self.rows, Layout::Planar, scratch);
+        self.col_plan.process_batch_with_scratch(data, self.cols, Layout::Interleaved, scratch);
+    }
+
+    // inverse transform that never allocates
+    pub fn inverse_with_scratch(&self, data: &mut [Complex<T>], scratch: &mut [Complex<T>]) {
+        self.check(data);
+        if data.is_empty() {
+            return;
+        }
+        self.row_plan.inverse_batch_with_scratch(data, self.rows, Layout::Planar, scratch);
+        self.col_plan.inverse_batch_with_scratch(data, self.cols, Layout::Interleaved, scratch);
+    }
+
+    fn check(&self, data: &[Complex<T>]) {
+        assert_eq!(data.len(), self.rows * self.cols, "{}x{} plan does not match data length {}",
+                   self.rows, self.cols, data.len());
+    }
+}
+
+// in-place 2d transform of a row-major rows x cols matrix
+// builds a throwaway plan, use Fft2Plan for repeated transforms
+pub fn fft2<T: Real>(data: &mut [Complex<T>], rows: usize, cols: usize) {
+    Fft2Plan::new(rows, cols).process(data);
+}
+
+// in-place inverse 2d transform, scaled by 1/(rows*cols)
+pub fn ifft2<T: Real>(data: &mut [Complex<T>], rows: usize, cols: usize) {
+    Fft2Plan::new(rows, cols).inverse(data);
+}
+
+// real matrix as complex, for transforming image data
+pub fn to_complex<T: Real>(data: &[T]) -> Vec<Complex<T>> {
+    data.iter().map(|&x| Complex{ re: x, im: T::zero() }).collect()
+}
+
+// moves the zero frequency from the corner to the center,
+// so low frequencies end up in the middle of a displayed spectrum
+pub fn shift<T: Copy>(data: &[T], rows: usize, cols: usize) -> Vec<T> {
+    assert_eq!(data.len(), rows * cols);
+    let mut out = Vec::with_capacity(data.len());
+    for r in 0..rows {
+        let src = (r + rows - rows / 2) % rows;
+        for c in 0..cols {
+            out.push(data[src * cols + (c + cols - cols / 2) % cols]);
+        }
+    }
+    out
+}
+
+// ln(1 + |X|) scaled to 0.0..=1.0, the usual way to display
+// a spectrum whose dc term dwarfs everything else
+pub fn log_magnitude<T: Real>(spectrum: &[Complex<T>]) -> Vec<T> {
+    let logs: Vec<T> = spectrum.iter().map(|c| c.norm().ln_1p()).collect();
+    let max = logs.iter().cloned().fold(T::zero(), T::max);
+    if max > T::zero() {
+        logs.iter().map(|&l| l / max).collect()
+    } else {
+        logs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+use std::f64::consts::PI;
+use super::*;
+use tests::complex_signal;
+
+fn dft2(data: &[Complex<f64>], rows: usize, cols: usize) -> Vec<Complex<f64>> {
+    let mut out = vec![Complex::zero(); rows * cols];
+    for u in 0..rows {
+        for v in 0..cols {
+            for r in 0..rows {
+                for c in 0..cols {
+                    let phi = -2.0 * PI * ((u * r) as f64 / rows as f64 + (v * c) as f64 / cols as f64);
+                    out[u * cols + v] += data[r * cols + c] * Complex::from_polar(&1.0, &phi);
+                }
+            }
+        }
+    }
+    out
+}
+
+#[test]
+    fn test_fft2_matches_dft(){
+        for &(rows, cols) in &[(8, 8), (6, 10), (1, 7), (13, 4)] {
+            let data: Vec<Complex<f64>> = complex_signal(rows * cols, 0);
+            let expected = dft2(&data, rows, cols);
+            let mut actual = data.clone();
+            fft2(&mut actual, rows, cols);
+            for (a, e) in actual.iter().zip(expected.iter()) {
+                assert!((a - e).norm() < 1e-9, "{}x{}", rows, cols);
+            }
+            ifft2(&mut actual, rows, cols);
+            for (a, b) in actual.iter().zip(data.iter()) {
+                assert!((a - b).norm() < 1e-12, "{}x{}", rows, cols);
+            }
+        }
+    }
+
+#[test]
+    fn test_fft2_periodicity(){
+        // vertical stripes 8 columns apart in a 32x64 image
+        let (rows, cols) = (32, 64);
+        let image: Vec<f32> = (0..rows * cols)
+            .map(|i| 0.5 + 0.5 * (2.0 * std::f32::consts::PI * (i % cols) as f32 / 8.0).cos())
+            .collect();
+        let mut spectrum = to_complex(&image);
+        fft2(&mut spectrum, rows, cols);
+
+        let magnitude = log_magnitude(&shift(&spectrum, rows, cols));
+        let peaks: Vec<usize> = (0..magnitude.len()).filter(|&i| magnitude[i] > 0.5).collect();
+        // dc in the center and the stripe frequency 64/8 = 8 bins either side
+        let center = rows / 2 * cols + cols / 2;
+        assert_eq!(peaks, vec![center - 8, center, center + 8]);
+        assert_eq!(magnitude[center], 1.0);
+    }
+}
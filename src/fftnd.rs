@@ -0,0 +1,258 @@
+// n-dimensional transforms over strided arrays
+// the dft is separable along every axis, so an n-d transform is a 1d
+// transform of every line along each chosen axis in turn
+
+use std::sync::Arc;
+use num_complex::Complex;
+use num_traits::Zero;
+use super::Real;
+use transform::{FftPlan, Planner};
+
+// row-major strides for shape, the last axis is contiguous
+pub fn contiguous_strides(shape: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1; shape.len()];
+    for d in (0..shape.len().saturating_sub(1)).rev() {
+        strides[d] = strides[d + 1] * shape[d + 1];
+    }
+    strides
+}
+
+// precomputed transform of the given axes of an array
+// shape and strides are in elements, strides[d] is the distance
+// between neighbouring points along axis d
+// strides must address every point of the shape at its own offset,
+// aliased points would be overwritten by the in-place transform;
+// this is checked in debug builds only, it costs a sort of all offsets
+pub struct FftNdPlan<T = f32> {
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+    // chosen axes with the 1d plan for their length,
+    // axes of equal length share a plan
+    axes: Vec<(usize, Arc<FftPlan<T>>)>,
+}
+
+impl<T: Real> FftNdPlan<T> {
+    pub fn new(shape: &[usize], strides: &[usize], axes: &[usize]) -> FftNdPlan<T> {
+        assert_eq!(shape.len(), strides.len(), "shape has {} axes, strides {}", shape.len(), strides.len());
+        for (i, &a) in axes.iter().enumerate() {
+            assert!(a < shape.len(), "axis {} out of range for {} axes", a, shape.len());
+            assert!(!axes[..i].contains(&a), "axis {} given twice", a);
+        }
+        debug_assert!(disjoint(shape, strides), "strides {:?} alias points of shape {:?}", strides, shape);
+        let mut planner = Planner::new();
+        let axes = axes.iter().map(|&a| (a, planner.plan(shape[a]))).collect();
+        FftNdPlan { shape: shape.to_vec(), strides: strides.to_vec(), axes }
+    }
+
+    // plan over all axes of a contiguous row-major array
+    pub fn contiguous(shape: &[usize]) -> FftNdPlan<T> {
+        let axes: Vec<usize> = (0..shape.len()).collect();
+        FftNdPlan::new(shape, &contiguous_strides(shape), &axes)
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    // smallest buffer the shape and strides fit in
+    pub fn min_len(&self) -> usize {
+        if self.shape.contains(&0) {
+            return 0;
+        }
+        1 + self.shape.iter().zip(self.strides.iter()).map(|(&n, &s)| (n - 1) * s).sum::<usize>()
+    }
+
+    // scratch length the _with_scratch transforms need:
+    // a gathered line plus the 1d plan's own scratch
+    pub fn scratch_len(&self) -> usize {
+        self.axes.iter()
+            .map(|(_, plan)| plan.len() + plan.scratch_len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    // in-place forward transform of the chosen axes
+    pub fn process(&self, data: &mut [Complex<T>]) {
+        let mut scratch = vec![Complex::zero(); self.scratch_len()];
+        self.process_with_scratch(data, &mut scratch);
+    }
+
+    // in-place inverse transform of the chosen axes,
+    // scaled by 1 / the product of their lengths
+    pub fn inverse(&self, data: &mut [Complex<T>]) {
+        let mut scratch = vec![Complex::zero(); self.scratch_len()];
+        self.inverse_with_scratch(data, &mut scratch);
+    }
+
+    // forward transform that never allocates
+    pub fn process_with_scratch(&self, data: &mut [Complex<T>], scratch: &mut [Complex<T>]) {
+        self.run(data, scratch, false)
+    }
+
+    // inverse transform that never allocates
+    pub fn inverse_with_scratch(&self, data: &mut [Complex<T>], scratch: &mut [Complex<T>]) {
+        self.run(data, scratch, true)
+    }
+
+    fn run(&self, data: &mut [Complex<T>], scratch: &mut [Complex<T>], inverse: bool) {
+        assert!(data.len() >= self.min_len(), "shape {:?} with strides {:?} needs {} points, got {}",
+                self.shape, self.strides, self.min_len(), data.len());
+        assert!(scratch.len() >= self.scratch_len(), "scratch of {} points, plan needs {}",
+                scratch.len(), self.scratch_len());
+        if self.min_len() == 0 {
+            return;
+        }
+
+        for &(axis, ref plan) in &self.axes {
+            let (len, stride) = (self.shape[axis], self.strides[axis]);
+            let (line, rest) = scratch.split_at_mut(len);
+            let lines = self.shape.iter().product::<usize>() / len;
+            for l in 0..lines {
+                let base = self.line_start(l, axis);
+                for (j, c) in line.iter_mut().enumerate() {
+                    *c = data[base + j * stride];
+                }
+                if inverse {
+                    plan.inverse_with_scratch(line, rest);
+                } else {
+                    plan.process_with_scratch(line, rest);
+                }
+                for (j, &c) in line.iter().enumerate() {
+                    data[base + j * stride] = c;
+                }
+            }
+        }
+    }
+
+    // offset of the first point of line l along axis, lines counted
+    // row-major over every other axis
+    fn line_start(&self, mut l: usize, axis: usize) -> usize {
+        let mut base = 0;
+        for d in (0..self.shape.len()).rev() {
+            if d != axis {
+                base += l % self.shape[d] * self.strides[d];
+                l /= self.shape[d];
+            }
+        }
+        base
+    }
+}
+
+// true when no two points of shape share an offset
+fn disjoint(shape: &[usize], strides: &[usize]) -> bool {
+    let mut offsets = vec![0];
+    for (&n, &stride) in shape.iter().zip(strides.iter()) {
+        offsets = offsets.iter()
+            .flat_map(|&o| (0..n).map(move |i| o + i * stride))
+            .collect();
+    }
+    offsets.sort_unstable();
+    offsets.windows(2).all(|p| p[0] != p[1])
+}
+
+// in-place transform of the given axes
+// builds a throwaway plan, use FftNdPlan for repeated transforms
+pub fn fftn<T: Real>(data: &mut [Complex<T>], shape: &[usize], strides: &[usize], axes: &[usize]) {
+    FftNdPlan::new(shape, strides, axes).process(data);
+}
+
+// in-place inverse transform of the given axes
+pub fn ifftn<T: Real>(data: &mut [Complex<T>], shape: &[usize], strides: &[usize], axes: &[usize]) {
+    FftNdPlan::new(shape, strides, axes).inverse(data);
+}
+
+#[cfg(test)]
+mod tests {
+use std::f64::consts::PI;
+use super::*;
+use fft2d;
+use tests::complex_signal;
+
+// direct dft of a contiguous 3d array over the chosen axes
+fn dft3(data: &[Complex<f64>], shape: &[usize], axes: &[usize]) -> Vec<Complex<f64>> {
+    let strides = contiguous_strides(shape);
+    let mut out = vec![Complex::zero(); data.len()];
+    for (o, out) in out.iter_mut().enumerate() {
+        let k: Vec<usize> = (0..3).map(|d| o / strides[d] % shape[d]).collect();
+        for (i, &x) in data.iter().enumerate() {
+            let n: Vec<usize> = (0..3).map(|d| i / strides[d] % shape[d]).collect();
+            // axes not transformed must match exactly
+            if (0..3).any(|d| !axes.contains(&d) && n[d] != k[d]) {
+                continue;
+            }
+            let phi: f64 = axes.iter().map(|&d| -2.0 * PI * (k[d] * n[d]) as f64 / shape[d] as f64).sum();
+            *out += x * Complex::from_polar(&1.0, &phi);
+        }
+    }
+    out
+}
+
+#[test]
+    fn test_fftn_matches_dft(){
+        let shape = [4, 6, 5];
+        let strides = contiguous_strides(&shape);
+        assert_eq!(strides, vec![30, 5, 1]);
+        let data: Vec<Complex<f64>> = complex_signal(120, 0);
+        for axes in &[vec![0, 1, 2], vec![1], vec![2, 0]] {
+            let expected = dft3(&data, &shape, axes);
+            let mut actual = data.clone();
+            fftn(&mut actual, &shape, &strides, axes);
+            for (a, e) in actual.iter().zip(expected.iter()) {
+                assert!((a - e).norm() < 1e-9, "axes {:?}", axes);
+            }
+            ifftn(&mut actual, &shape, &strides, axes);
+            for (a, b) in actual.iter().zip(data.iter()) {
+                assert!((a - b).norm() < 1e-12, "axes {:?}", axes);
+            }
+        }
+    }
+
+#[test]
+    fn test_fftn_strided(){
+        // a 6x8 matrix stored transposed with padded columns:
+        // element (r, c) at c * 7 + r
+        let (rows, cols) = (6, 8);
+        let matrix: Vec<Complex<f64>> = complex_signal(rows * cols, 0);
+        let mut expected = matrix.clone();
+        fft2d::fft2(&mut expected, rows, cols);
+
+        // padding holds a marker that must survive the transform
+        let marker = Complex{ re: 7.0, im: -7.0 };
+        let mut stored = vec![marker; cols * 7];
+        for r in 0..rows {
+            for c in 0..cols {
+                stored[c * 7 + r] = matrix[r * cols + c];
+            }
+        }
+        let plan = FftNdPlan::new(&[rows, cols], &[1, 7], &[0, 1]);
+        assert_eq!(plan.min_len(), 55);
+        plan.process(&mut stored);
+        for r in 0..rows {
+            for c in 0..cols {
+                assert!((stored[c * 7 + r] - expected[r * cols + c]).norm() < 1e-9);
+            }
+        }
+        // every slot outside the logical shape is left alone
+        for (i, &v) in stored.iter().enumerate().filter(|&(i, _)| i % 7 >= rows) {
+            assert_eq!(v, marker, "padding at {} overwritten", i);
+        }
+        assert_eq!(stored.iter().filter(|&&v| v == marker).count(), cols);
+    }
+
+#[test]
+    fn test_fftn_aliasing_strides(){
+        assert!(disjoint(&[6, 8], &[1, 7]));
+        assert!(disjoint(&[4, 6, 5], &contiguous_strides(&[4, 6, 5])));
+        // a single point along an axis may have any stride
+        assert!(disjoint(&[1, 8], &[0, 1]));
+        assert!(disjoint(&[0, 8], &[0, 0]));
+        // rows overlapping by one point, and a broadcast axis
+        assert!(!disjoint(&[6, 8], &[7, 1]));
+        assert!(!disjoint(&[2, 8], &[0, 1]));
+
+        if cfg!(debug_assertions) {
+            let result = std::panic::catch_unwind(|| FftNdPlan::<f64>::new(&[6, 8], &[7, 1], &[0, 1]));
+            assert!(result.is_err());
+        }
+    }
+}
@@ -0,0 +1,234 @@
+// grayscale image loading and saving for 2d analysis
+// pgm (P2 ascii and P5 binary) is read directly, png through the png crate
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+use png;
+
+// row-major gray levels in 0.0..=1.0
+pub struct Image {
+    pub data: Vec<f32>,
+    pub width: usize,
+    pub height: usize,
+}
+
+fn is_png(path: &str) -> bool {
+    Path::new(path).extension()
+        .map(|e| e.to_string_lossy().eq_ignore_ascii_case("png"))
+        .unwrap_or(false)
+}
+
+// loads a png or pgm file, chosen by extension, as grayscale
+pub fn load(path: &str) -> Result<Image, String> {
+    if is_png(path) {
+        load_png(path)
+    } else {
+        let mut bytes = vec![];
+        File::open(path)
+            .and_then(|mut f| f.read_to_end(&mut bytes))
+            .map_err(|e| format!("{}: {}", path, e))?;
+        parse_pgm(&bytes).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+// saves gray levels in 0.0..=1.0 as 8 bit png or binary pgm, chosen by extension
+pub fn save(path: &str, image: &Image) -> Result<(), String> {
+    let pixels: Vec<u8> = image.data.iter()
+        .map(|&v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+        .collect();
+    let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut writer = BufWriter::new(file);
+    if is_png(path) {
+        let mut encoder = png::Encoder::new(writer, image.width as u32, image.height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()
+            .and_then(|mut w| w.write_image_data(&pixels))
+            .map_err(|e| format!("{}: {}", path, e))
+    } else {
+        write!(writer, "P5\n{} {}\n255\n", image.width, image.height)
+            .and_then(|_| writer.write_all(&pixels))
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+fn load_png(path: &str) -> Result<Image, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut decoder = png::Decoder::new(file);
+    // palettes expanded and 16 bit depths stripped to 8
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| format!("{}: {}", path, e))?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| format!("{}: {}", path, e))?;
+
+    let channels = info.color_type.samples();
+    let (width, height) = (info.width as usize, info.height as usize);
+    let mut data = Vec::with_capacity(width * height);
+    for row in buf.chunks(info.line_size).take(height) {
+        for px in row.chunks(channels).take(width) {
+            // luma of rgb, alpha ignored
+            let gray = if channels >= 3 {
+                0.299 * px[0] as f32 + 0.587 * px[1] as f32 + 0.114 * px[2] as f32
+            } else {
+                px[0] as f32
+            };
+            data.push(gray / 255.0);
+        }
+    }
+    Ok(Image { data, width, height })
+}
+
+// whitespace separated header token, skipping # comments
+fn token(bytes: &[u8], pos: &mut usize) -> Result<usize, String> {
+    loop {
+        while *pos < bytes.len() && bytes[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+        if *pos < bytes.len() && bytes[*pos] == b'#' {
+            while *pos < bytes.len() && bytes[*pos] != b'\n' {
+                *pos += 1;
+            }
+        } else {
+            break;
+        }
+    }
+    let start = *pos;
+    while *pos < bytes.len() && !bytes[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+    std::str::from_utf8(&bytes[start..*pos])
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| "invalid pgm header".to_string())
+}
+
+fn parse_pgm(bytes: &[u8]) -> Result<Image, String> {
+    let binary = match bytes.get(0..2) {
+        Some(b"P5") => true,
+        Some(b"P2") => false,
+        _ => return Err("not a pgm file".to_string()),
+    };
+    let mut pos = 2;
+    let width = token(bytes, &mut pos)?;
+    let height = token(bytes, &mut pos)?;
+    let maxval = token(bytes, &mut pos)?;
+    if maxval == 0 || maxval > 65535 {
+        return Err(format!("invalid pgm maxval {}", maxval));
+    }
+    let depth = if maxval < 256 { 1 } else { 2 };
+    // sizes are checked against the file before anything is allocated
+    let count = width.checked_mul(height)
+        .filter(|n| n.checked_mul(depth).is_some())
+        .ok_or_else(|| format!("pgm of {}x{} pixels is too large", width, height))?;
+    let scale = 1.0 / maxval as f32;
+
+    let data = if binary {
+        // single whitespace byte ends the header
+        let raster = &bytes[(pos + 1).min(bytes.len())..];
+        if raster.len() < count * depth {
+            return Err("truncated pgm raster".to_string());
+        }
+        raster.chunks(depth)
+            .take(count)
+            .map(|v| {
+                // 16 bit values are big-endian
+                let level = v.iter().fold(0usize, |acc, &b| acc << 8 | b as usize);
+                level as f32 * scale
+            })
+            .collect()
+    } else {
+        // every ascii value takes a digit and a separator
+        if (bytes.len() - pos) / 2 + 1 < count {
+            return Err("truncated pgm raster".to_string());
+        }
+        (0..count)
+            .map(|_| token(bytes, &mut pos)
+                 .map(|v| v as f32 * scale)
+                 .map_err(|_| "truncated pgm raster".to_string()))
+            .collect::<Result<Vec<f32>, String>>()?
+    };
+    Ok(Image { data, width, height })
+}
+
+#[cfg(test)]
+mod tests {
+use super::*;
+use std::env;
+use std::fs;
+
+fn close(a: &[f32], b: &[f32]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| (x - y).abs() < 1e-6)
+}
+
+fn temp_path(name: &str) -> String {
+    env::temp_dir()
+        .join(format!("fft-image-test-{}-{}", std::process::id(), name))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+    fn test_parse_pgm_ascii(){
+        let image = parse_pgm(b"P2\n# a comment\n3 2 # trailing\n4\n0 1 2\n# between rows\n3 4 0\n").unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert!(close(&image.data, &[0.0, 0.25, 0.5, 0.75, 1.0, 0.0]));
+    }
+
+#[test]
+    fn test_parse_pgm_binary(){
+        let mut bytes = b"P5\n# comment\n2 2\n255\n".to_vec();
+        bytes.extend_from_slice(&[0, 51, 255, 102]);
+        let image = parse_pgm(&bytes).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert!(close(&image.data, &[0.0, 0.2, 1.0, 0.4]));
+
+        // maxval over 255 stores big-endian 16 bit samples
+        let mut bytes = b"P5 2 1 65535\n".to_vec();
+        bytes.extend_from_slice(&[0xff, 0xff, 0x80, 0x00]);
+        let image = parse_pgm(&bytes).unwrap();
+        assert!(close(&image.data, &[1.0, 32768.0 / 65535.0]));
+
+        let image = parse_pgm(b"P2 2 1 1000 1000 250").unwrap();
+        assert!(close(&image.data, &[1.0, 0.25]));
+    }
+
+#[test]
+    fn test_parse_pgm_errors(){
+        let truncated = |bytes: &[u8]| parse_pgm(bytes).err().unwrap();
+        assert_eq!(truncated(b"P5 4 4 255\n\x01\x02\x03"), "truncated pgm raster");
+        assert_eq!(truncated(b"P5 2 1 65535\n\x01\x02\x03"), "truncated pgm raster");
+        assert_eq!(truncated(b"P2 3 1 255 1 2"), "truncated pgm raster");
+        assert_eq!(truncated(b"P2 3 1 255 1 2 x"), "truncated pgm raster");
+
+        assert!(parse_pgm(b"P6 1 1 255 0").is_err());
+        assert!(parse_pgm(b"P5 1 1 0 0").is_err());
+        assert!(parse_pgm(b"P5 1 x 255 0").is_err());
+        // header sizes whose product overflows are rejected up front
+        let huge = format!("P5 {} {} 255\n", usize::MAX / 2, 3);
+        assert!(parse_pgm(huge.as_bytes()).err().unwrap().contains("too large"));
+        let huge = format!("P5 {} {} 65535\n", usize::MAX / 4, 3);
+        assert!(parse_pgm(huge.as_bytes()).err().unwrap().contains("too large"));
+    }
+
+#[test]
+    fn test_save_load_roundtrip(){
+        let data: Vec<f32> = (0..12).map(|i| i as f32 / 11.0).collect();
+        let image = Image{ data: data.clone(), width: 4, height: 3 };
+        for name in &["roundtrip.png", "roundtrip.pgm"] {
+            let path = temp_path(name);
+            save(&path, &image).unwrap();
+            let loaded = load(&path);
+            fs::remove_file(&path).unwrap();
+            let loaded = loaded.unwrap();
+            assert_eq!((loaded.width, loaded.height), (4, 3));
+            // 8 bit quantization
+            for (a, b) in loaded.data.iter().zip(data.iter()) {
+                assert!((a - b).abs() <= 0.5 / 255.0 + 1e-6, "{}: {} vs {}", name, a, b);
+            }
+        }
+
+        assert!(load(&temp_path("missing.pgm")).is_err());
+        assert!(load(&temp_path("missing.png")).is_err());
+    }
+}
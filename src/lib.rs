@@ -1,33 +1,81 @@
 // FFT lib
-// (actually DSFT - damn slow fourier transform right now)
 extern crate num_complex;
+extern crate num_traits;
 
 pub mod util;
-
-use std::f32::consts::PI;
+pub mod transform;
+pub mod real;
+pub mod window;
+pub mod stft;
+pub mod parallel;
+pub mod simd;
+pub mod batch;
+pub mod fft2d;
+pub mod fftnd;
+pub mod dct;
+pub mod lombscargle;
+pub mod psd;
+pub mod multitaper;
+pub mod parametric;
+pub mod convolve;
+pub mod correlate;
+
+pub use transform::{FftPlan, Planner};
+pub use window::Window;
+pub use simd::Kernel;
+
+use std::fmt;
 use num_complex::Complex;
+use num_traits::{Float, FloatConst, NumAssign};
+
+// floating point types analysis can run in, f32 or f64
+pub trait Real: Float + FloatConst + NumAssign + Default + fmt::Debug + fmt::Display + Send + Sync + 'static {
+    fn from_f64(x: f64) -> Self;
+    fn as_f64(self) -> f64;
+
+    // one radix-2 butterfly stage, see simd::butterflies_scalar
+    fn butterflies(_kernel: Kernel, data: &mut [Complex<Self>], twiddles: &[Complex<Self>], len: usize) {
+        simd::butterflies_scalar(data, twiddles, len)
+    }
+}
 
-const I: Complex<f32> = Complex { re: 0.0, im: 1.0 };
+impl Real for f32 {
+    fn from_f64(x: f64) -> f32 { x as f32 }
+    fn as_f64(self) -> f64 { self as f64 }
 
-pub struct Phasor {
-    pub frequency: f32,
-    pub complex: Complex<f32>,
+    fn butterflies(kernel: Kernel, data: &mut [Complex<f32>], twiddles: &[Complex<f32>], len: usize) {
+        simd::butterflies_f32(kernel, data, twiddles, len)
+    }
 }
 
-pub struct Sample {
+impl Real for f64 {
+    fn from_f64(x: f64) -> f64 { x }
+    fn as_f64(self) -> f64 { self }
+
+    fn butterflies(kernel: Kernel, data: &mut [Complex<f64>], twiddles: &[Complex<f64>], len: usize) {
+        simd::butterflies_f64(kernel, data, twiddles, len)
+    }
+}
+
+pub struct Phasor<T = f32> {
+    pub frequency: T,
+    pub complex: Complex<T>,
+}
+
+pub struct Sample<T = f32> {
     // sample data [-1.0-1.0,...]
-    pub data: Vec<f32>,
+    pub data: Vec<T>,
     // samplerate eg: 44_100
     pub rate: usize
 }
 
-impl Sample {
+impl<T: Real> Sample<T> {
     pub fn is_empty(&self) -> bool {
         self.data.len() == 0
     }
 
-    pub fn max_amplitude(&self) -> f32 {
-        self.data.iter().fold(0.0, |acc: f32, &xy|{
+    pub fn max_amplitude(&self) -> T {
+        self.data.iter().fold(T::zero(), |acc: T, &xy|{
             acc.max(xy.abs())
         })
     }
@@ -39,13 +87,13 @@ impl Sample {
             .enumerate()
             .step_by(ss)
             .map(|(i, &a)| {
-                (i as f64 * dt, a as f64)
+                (i as f64 * dt, a.as_f64())
             })
             .collect()
     }
 
-    pub fn time(&self) -> f32 {
-        (self.data.len() as f64 / self.rate as f64) as f32
+    pub fn time(&self) -> T {
+        T::from_f64(self.data.len() as f64 / self.rate as f64)
     }
 
     // takes every peak value (where delta values cross)
@@ -54,12 +102,12 @@ impl Sample {
             .enumerate()
             .skip(1)
             .step_by(2)
-            .fold((vec![],0.0), |mut acc, (idx,_)| {
+            .fold((vec![],T::zero()), |mut acc, (idx,_)| {
                 let d2 = self.data[idx].abs() - self.data[idx-1].abs();
 
                 // track derivate change
-                if acc.1 > 0.0 && d2 < 0.0 ||
-                    acc.1 < 0.0 && d2 > 0.0 {
+                if acc.1 > T::zero() && d2 < T::zero() ||
+                    acc.1 < T::zero() && d2 > T::zero() {
                     acc.0.push(idx - 1);
                 }
 
@@ -68,10 +116,18 @@ impl Sample {
     }
 
 
+    // all data points as (time, amplitude)
+    pub fn points(&self) -> Vec<(T,T)> {
+        self.data.iter()
+            .enumerate()
+            .map(|(i, &a)| (T::from_f64(i as f64 / self.rate as f64), a))
+            .collect()
+    }
+
     // extract peaks-only data
-    pub fn simplify(&self) -> Vec<(f32,f32)> {
+    pub fn simplify(&self) -> Vec<(T,T)> {
         self.peaks().into_iter()
-            .map(|p| ((p as f64 / self.rate as f64) as f32, self.data[p]))
+            .map(|p| (T::from_f64(p as f64 / self.rate as f64), self.data[p]))
             .collect()
     }
 }
@@ -81,46 +137,162 @@ impl Sample {
 // Argument f is for winding frequency
 // data: vector of (time, amplitude)
 // sf : sampling frequency (eg. 44100hz)
-pub fn graph_circle(data: &[(f32,f32)], f: f32) -> Vec<Complex<f32>> {
+pub fn graph_circle<T: Real>(data: &[(T,T)], f: T) -> Vec<Complex<T>> {
+    let mut out = vec![Complex{re: T::zero(), im: T::zero()}; data.len()];
+    graph_circle_into(data, f, &mut out);
+    out
+}
+
+// graph_circle into a caller owned buffer of data.len() points,
+// for callers that must not allocate
+pub fn graph_circle_into<T: Real>(data: &[(T,T)], f: T, out: &mut [Complex<T>]) {
+    assert_eq!(data.len(), out.len(), "{} data points, output holds {}", data.len(), out.len());
     // precalculate 2πf
-    let fc = 2.0 * PI * f;
+    let fc = T::from_f64(2.0) * T::PI() * f;
     // winding machine
-    data.iter()
-        .map(|&(t,a)| {
-            let c = (I * fc * t).exp() * a;
-            // TODO: why does this have to be inverse
-            Complex{re: c.im, im: c.re}
-        })
-    .collect()
+    for (c, &(t,a)) in out.iter_mut().zip(data.iter()) {
+        *c = wind(fc, t, a);
+    }
 }
 
-// calculates mean average vector length from array of (f32,64)
-fn calc_mean(data: Vec<Complex<f32>>) -> Complex<f32> {
-    data.into_iter()
-        .enumerate()
-        .fold(Complex{re:0.0,im:0.0},
-              |acc, (i, c)| {
-                  ((acc * i as f32) + c) / (i + 1) as f32
-              })
+// single point of the winding machine, fc = 2πf
+fn wind<T: Real>(fc: T, t: T, a: T) -> Complex<T> {
+    let c = Complex::from_polar(&a, &(fc * t));
+    // TODO: why does this have to be inverse
+    Complex{re: c.im, im: c.re}
+}
+
+// complex mean of the wound points, sum / n, whose length and
+// angle are the amplitude and phase at the winding frequency
+// running sum divided once, so long inputs keep their precision
+fn calc_mean<T: Real>(data: Vec<Complex<T>>) -> Complex<T> {
+    let n = data.len().max(1);
+    let sum = data.into_iter().fold(Complex{re: T::zero(), im: T::zero()}, |acc, c| acc + c);
+    sum / T::from_f64(n as f64)
+}
+
+// Options for FT analysis
+#[derive(Clone, Default)]
+pub struct AnalyzeOptions {
+    // wind the full uniformly sampled data instead of
+    // peaks-only simplified data
+    pub full_data: bool,
+    // window applied to the data before analysis,
+    // amplitudes are corrected by its coherent gain
+    pub window: Window,
+    // threads to split the frequency sweep across,
+    // 0 or 1 runs serially
+    pub threads: usize,
+}
+
+impl AnalyzeOptions {
+    // windowed data points fed to the winding machine
+    pub fn points<T: Real>(&self, sample: &Sample<T>) -> Vec<(T,T)> {
+        self.select(&self.window.apply_sample(sample))
+    }
+
+    fn select<T: Real>(&self, sample: &Sample<T>) -> Vec<(T,T)> {
+        if self.full_data {
+            sample.points()
+        } else {
+            sample.simplify()
+        }
+    }
+}
+
+// Returns sampled FT analysis vector with default options
+pub fn analyze<T: Real>(sample: &Sample<T>, min: T, max: T, ss: T) -> Vec<(T,Complex<T>)> {
+    analyze_with(sample, min, max, ss, &AnalyzeOptions::default())
 }
 
 // Returns sampled FT analysis vector
-pub fn analyze(sample: &Sample, min: f32, max: f32, ss: f32) -> Vec<(f32,Complex<f32>)> {
-    println!("FT analysis: {} => {}, step {} hz", min, max, ss);
+// without full_data every frequency winds the peaks-only data
+// with full_data the sweep uses the fft when it lands exactly on the
+// bin grid, which agrees with graph_circle over sample.points()
+// within 1e-3 per phasor for signals of unit amplitude, the zoom fft
+// when that is cheaper than evaluating every frequency on its own,
+// and goertzel otherwise
+pub fn analyze_with<T: Real>(sample: &Sample<T>, min: T, max: T, ss: T, options: &AnalyzeOptions) -> Vec<(T,Complex<T>)> {
+    println!("FT analysis: {} => {}, step {} hz, {} window", min, max, ss, options.window);
+
+    let windowed = options.window.apply_sample(sample);
+    let sample = &windowed;
+
+    if options.full_data && on_bin_grid(sample, min, ss) {
+        return analyze_fft(sample, min, max, ss);
+    }
 
     // calculate data points
-    let mut ft_data = vec![];
-    let mut f = min;
+    let frequencies = sweep(min, max, ss);
+
+    if options.full_data {
+        // narrow bands of many steps go through the zoom fft
+        if zoom_is_cheaper(sample.data.len(), frequencies.len()) {
+            return transform::zoom(sample, min, ss, frequencies.len())
+                .into_iter()
+                .enumerate()
+                .map(|(k, c)| (T::from_f64(min.as_f64() + k as f64 * ss.as_f64()), c))
+                .collect();
+        }
+        // full data is uniformly sampled, so goertzel can replace winding
+        return parallel::map(&frequencies, options.threads, |&f| {
+            (f, goertzel(&sample.data, sample.rate, f))
+        });
+    }
 
-    let data = sample.simplify();
+    let data = options.select(sample);
 
-    while f <= max {
+    // every frequency is independent
+    parallel::map(&frequencies, options.threads, |&f| {
         // calculate revolutions around unit circle
         let processed = graph_circle(&data, f);
         // calculate mean from y-axis values
-        let mx = calc_mean(processed);
-        ft_data.push((f, mx));
+        (f, calc_mean(processed))
+    })
+}
+
+// frequencies visited by a min..max sweep
+fn sweep<T: Real>(min: T, max: T, ss: T) -> Vec<T> {
+    let mut frequencies = vec![];
+    let mut f = min;
+    while f <= max {
+        frequencies.push(f);
+        f += ss;
+    }
+    frequencies
+}
+
+// compares count goertzel passes over n points with the
+// three transforms of a chirp-z over the same band
+fn zoom_is_cheaper(n: usize, count: usize) -> bool {
+    let len = (n + count).next_power_of_two() as f64;
+    (n * count) as f64 > 3.0 * len * len.log2() + 4.0 * len
+}
+
+// checks if sweep min and stepsize match fft bin spacing
+fn on_bin_grid<T: Real>(sample: &Sample<T>, min: T, ss: T) -> bool {
+    if sample.is_empty() {
+        return false;
+    }
+    let df = transform::bin_spacing(sample).as_f64();
+    let (min, ss) = (min.as_f64(), ss.as_f64());
+    let tolerance = df * 1e-4;
+    let offset = min / df;
+    (ss - df).abs() <= tolerance && (offset - offset.round()).abs() * df <= tolerance
+}
 
+// FT analysis vector picked from the real input half spectrum
+fn analyze_fft<T: Real>(sample: &Sample<T>, min: T, max: T, ss: T) -> Vec<(T,Complex<T>)> {
+    let n = sample.data.len();
+    let spectrum = real::rfft(sample);
+    let df = spectrum.spacing();
+
+    let mut ft_data = vec![];
+    let mut f = min;
+    while f <= max {
+        // negative and beyond-rate frequencies alias back into range
+        let k = ((f / df).round().as_f64() as isize).rem_euclid(n as isize) as usize;
+        ft_data.push((f, transform::bin_to_phasor(spectrum.bin(k), n)));
         f += ss;
     }
 
@@ -129,15 +301,41 @@ pub fn analyze(sample: &Sample, min: f32, max: f32, ss: f32) -> Vec<(f32,Complex
 
 // Returns FT analysis float value
 // for a frequency value
-pub fn analyze_freq(data: &[(f32,f32)], f: f32) -> Complex<f32> {
-    calc_mean(graph_circle(data, f))
+// same as calc_mean(graph_circle(data, f)) without the allocation
+pub fn analyze_freq<T: Real>(data: &[(T,T)], f: T) -> Complex<T> {
+    let fc = T::from_f64(2.0) * T::PI() * f;
+    let sum = data.iter()
+        .fold(Complex{re: T::zero(), im: T::zero()}, |acc, &(t,a)| acc + wind(fc, t, a));
+    sum / T::from_f64(data.len().max(1) as f64)
+}
+
+// Returns FT analysis value for a frequency value over uniformly
+// sampled data, using the goertzel second order resonator
+// O(n) without allocation, in the same convention as analyze_freq
+pub fn goertzel<T: Real>(data: &[T], rate: usize, f: T) -> Complex<T> {
+    if data.is_empty() {
+        return Complex{re: T::zero(), im: T::zero()};
+    }
+    // resonator runs in f64, error would otherwise grow with length
+    let w = 2.0 * std::f64::consts::PI * f.as_f64() / rate as f64;
+    let coeff = 2.0 * w.cos();
+    let (s1, s2) = data.iter().fold((0.0, 0.0), |(s1, s2), &x| {
+        (x.as_f64() + coeff * s1 - s2, s1)
+    });
+
+    // y = s[N-1] - e^(-iw) s[N-2] = sum x[n] e^(iw(N-1-n))
+    // X = e^(-iw(N-1)) y = sum x[n] e^(-iwn)
+    let n = data.len();
+    let y = Complex{re: s1 - w.cos() * s2, im: w.sin() * s2};
+    let x = y * Complex::from_polar(&1.0, &(-w * (n - 1) as f64));
+    transform::bin_to_phasor(Complex{re: T::from_f64(x.re), im: T::from_f64(x.im)}, n)
 }
 
 // finds a local max inside
-pub fn max(data: &[Phasor]) -> usize {
+pub fn max<T: Real>(data: &[Phasor<T>]) -> usize {
     data.iter()
         .enumerate()
-        .fold((0,Complex{re: 0.0, im: 0.0}),|acc,(idx,p)| {
+        .fold((0,Complex{re: T::zero(), im: T::zero()}),|acc,(idx,p)| {
             if acc.1.to_polar().0 > p.complex.to_polar().0 {
                 acc
             }
@@ -149,13 +347,44 @@ pub fn max(data: &[Phasor]) -> usize {
 
 #[cfg(test)]
 mod tests {
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
 use std::f32::consts::PI;
 use super::*;
 
+// counts allocations per thread, so tests running in
+// parallel do not see each other's
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+// allocations made on this thread while running f
+pub fn allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(|n| n.get());
+    f();
+    ALLOCATIONS.with(|n| n.get()) - before
+}
+
 #[test]
     fn test_circle_single(){
         let sine = util::sinewaves(1.0, 1000, &[(5.0,0.0)]);
-        let circle = graph_circle(&sine, 5.0);
+        let circle = graph_circle(&sine.points(), 5.0);
         let center = calc_mean(circle);
         assert!(center.re > 0.45);
     }
@@ -164,11 +393,11 @@ use super::*;
     fn test_circle_multi(){
         let sine = util::sinewaves(1.0, 1000, &[(5.0,0.0),(10.0,0.0)]);
 
-        let circle = graph_circle(&sine, 5.0);
+        let circle = graph_circle(&sine.points(), 5.0);
         let center = calc_mean(circle);
         assert!(center.re > 0.45);
 
-        let circle = graph_circle(&sine, 10.0);
+        let circle = graph_circle(&sine.points(), 10.0);
         let center = calc_mean(circle);
         assert!(center.re > 0.45);
     }
@@ -177,7 +406,7 @@ use super::*;
     fn test_circle_phase(){
         let sine = util::sinewaves(1.0, 1000, &[(5.0,90.0)]);
 
-        let circle = graph_circle(&sine, 5.0);
+        let circle = graph_circle(&sine.points(), 5.0);
         let polar = calc_mean(circle).to_polar();
         let deg = polar.1 * 180.0 / PI;
         assert!(polar.0 > 0.45);
@@ -188,13 +417,13 @@ use super::*;
     fn test_circle_phase_multi(){
         let sine = util::sinewaves(1.0, 1000, &[(5.0,90.0),(60.0,0.0)]);
 
-        let circle = graph_circle(&sine, 5.0);
+        let circle = graph_circle(&sine.points(), 5.0);
         let polar = calc_mean(circle).to_polar();
         let deg = polar.1 * 180.0 / PI;
         assert!(polar.0 > 0.45);
         assert_eq!(deg.round(), 90.0);
 
-        let circle = graph_circle(&sine, 60.0);
+        let circle = graph_circle(&sine.points(), 60.0);
         let polar = calc_mean(circle).to_polar();
         let deg = polar.1 * 180.0 / PI;
         assert!(polar.0 > 0.45);
@@ -202,18 +431,241 @@ use super::*;
 
         let sine = util::sinewaves(1.0, 1000, &[(5.0,180.0),(60.0,270.0)]);
 
-        let circle = graph_circle(&sine, 5.0);
+        let circle = graph_circle(&sine.points(), 5.0);
         let polar = calc_mean(circle).to_polar();
         let deg = polar.1 * 180.0 / PI;
         assert!(polar.0 > 0.45);
         println!("{}", deg);
         assert_eq!(deg.round(), -180.0);
 
-        let circle = graph_circle(&sine, 60.0);
+        let circle = graph_circle(&sine.points(), 60.0);
         let polar = calc_mean(circle).to_polar();
         let deg = polar.1 * 180.0 / PI;
         println!("{}", deg);
         assert!(polar.0 > 0.45);
         assert_eq!(deg.round(),-90.0);
     }
+
+// deterministic pseudo random noise in -1.0..1.0
+pub fn noise(len: usize, seed: u32) -> Vec<f32> {
+    let mut state = seed;
+    (0..len).map(|_| {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (state >> 8) as f32 / (1u32 << 23) as f32 - 1.0
+    }).collect()
+}
+
+// deterministic smooth complex test signal, seed 0 is
+// sin(0.37 i) + i cos(0.11 i) and every seed its own pattern
+pub fn complex_signal<T: Real>(len: usize, seed: usize) -> Vec<Complex<T>> {
+    let (offset, scale) = (seed as f64, 1.0 + seed as f64);
+    (0..len)
+        .map(|i| Complex{
+            re: T::from_f64((i as f64 * 0.37 + offset).sin()),
+            im: T::from_f64((i as f64 * 0.11 * scale).cos()),
+        })
+        .collect()
+}
+
+#[test]
+    fn test_analyze_full_data(){
+        // 2.5 hz steps are off the bin grid and too few for the zoom fft,
+        // so both paths wind
+        let mut sample = util::sinewaves(1.0, 1000, &[(5.0,90.0),(60.0,0.0)]);
+        let full = AnalyzeOptions{ full_data: true, ..AnalyzeOptions::default() };
+
+        // amplitude error at the tones and mean leakage elsewhere
+        let quantify = |ft: &[(f32,Complex<f32>)]| {
+            let error = ft.iter()
+                .filter(|&&(f, _)| f == 5.0 || f == 60.0)
+                .fold(0.0f32, |acc, &(_, c)| acc.max((c.norm() - 0.5).abs()));
+            let off: Vec<f32> = ft.iter()
+                .filter(|&&(f, _)| (f - 5.0).abs() > 2.0 && (f - 60.0).abs() > 2.0)
+                .map(|&(_, c)| c.norm())
+                .collect();
+            (error, off.iter().sum::<f32>() / off.len() as f32)
+        };
+
+        let exact = quantify(&analyze_with(&sample, 2.5, 100.0, 2.5, &full));
+        let simplified = quantify(&analyze(&sample, 2.5, 100.0, 2.5));
+        println!("clean: full {:?}, simplified {:?}", exact, simplified);
+        assert!(exact.0 < 1e-2);
+        assert!(simplified.0 > 0.1);
+
+        // noise spreads into the peaks-only input
+        let len = sample.data.len();
+        for (a, n) in sample.data.iter_mut().zip(noise(len, 7)) {
+            *a += 0.2 * n;
+        }
+        let exact = quantify(&analyze_with(&sample, 2.5, 100.0, 2.5, &full));
+        let simplified = quantify(&analyze(&sample, 2.5, 100.0, 2.5));
+        println!("noisy: full {:?}, simplified {:?}", exact, simplified);
+        assert!(exact.0 < 0.05);
+        assert!(simplified.0 > 0.1);
+        assert!(simplified.1 > 2.0 * exact.1);
+    }
+
+#[test]
+    fn test_sinewave_len(){
+        // as many samples as stepping by 1 / sr takes to reach t,
+        // f32 steps fall just short of 1 s and take one more
+        assert_eq!(util::sinewave(5.0f32, 0.0, 1.0, 1000, 1.0).data.len(), 1001);
+        assert_eq!(util::sinewave(5.0f32, 0.0, 0.0015, 1000, 1.0).data.len(), 2);
+    }
+
+#[test]
+    fn test_analyze_window(){
+        // 10.5 hz falls between bins and leaks across the spectrum
+        let sine = util::sinewave(10.5, 0.0, 1.0, 1024, 1.0);
+        let leakage = |window| {
+            let options = AnalyzeOptions{ window, full_data: true, ..AnalyzeOptions::default() };
+            let ft = analyze_with(&sine, 0.0, 100.0, 1.0, &options);
+            ft.iter().filter(|&&(f, _)| f >= 30.0).fold(0.0f32, |acc, &(_, c)| acc.max(c.norm()))
+        };
+        let rect = leakage(Window::Rectangular);
+        let hann = leakage(Window::Hann);
+        let bh = leakage(Window::BlackmanHarris);
+        assert!(hann < rect / 10.0, "hann {} rect {}", hann, rect);
+        assert!(bh < hann / 10.0, "blackman-harris {} hann {}", bh, hann);
+
+        // flat top keeps the amplitude of the off-bin tone
+        let options = AnalyzeOptions{ window: Window::FlatTop, full_data: true, ..AnalyzeOptions::default() };
+        let ft = analyze_with(&sine, 0.0, 100.0, 1.0, &options);
+        let peak = ft.iter().fold(0.0f32, |acc, &(_, c)| acc.max(c.norm()));
+        assert!((peak - 0.5).abs() < 0.01, "flat top peak {}", peak);
+    }
+
+#[test]
+    fn test_graph_circle_into(){
+        let data = util::sinewaves(1.0, 1000, &[(5.0,0.0)]).points();
+        let expected = graph_circle(&data, 5.0);
+        let mut out = vec![Complex{re: 0.0, im: 0.0}; data.len()];
+        assert!(allocations(|| { graph_circle(&data, 5.0); }) > 0);
+        assert_eq!(allocations(|| graph_circle_into(&data, 5.0, &mut out)), 0);
+        assert_eq!(out, expected);
+    }
+
+#[test]
+    fn test_goertzel(){
+        let sine = util::sinewaves(1.0, 1000, &[(5.0,90.0),(60.0,0.0)]);
+        let points = sine.points();
+        for &f in &[5.0, 10.5, 60.0, 60.25, 499.0] {
+            let c = goertzel(&sine.data, sine.rate, f);
+            let winding = analyze_freq(&points, f);
+            assert!((c - winding).norm() < 1e-3, "{} hz: {} vs {}", f, c, winding);
+            assert_eq!(winding, calc_mean(graph_circle(&points, f)));
+        }
+
+        // long input keeps its precision
+        let data: Vec<f32> = (0..60 * 44_100)
+            .map(|i| (2.0 * std::f64::consts::PI * 50.0 * i as f64 / 44_100.0).sin() as f32)
+            .collect();
+        let polar = goertzel(&data, 44_100, 50.0).to_polar();
+        assert!((polar.0 - 0.5).abs() < 1e-3, "{}", polar.0);
+    }
+
+#[test]
+    fn test_analyze_zoom(){
+        // narrow band at fine resolution picks the chirp-z
+        let sine = util::sinewaves(1.0, 1000, &[(50.0,30.0)]);
+        assert!(zoom_is_cheaper(sine.data.len(), sweep(49.0, 51.0, 0.01).len()));
+        assert!(!zoom_is_cheaper(sine.data.len(), sweep(49.0, 51.0, 0.5).len()));
+
+        let full = AnalyzeOptions{ full_data: true, ..AnalyzeOptions::default() };
+        let zoomed = analyze_with(&sine, 49.0, 51.0, 0.01, &full);
+        assert_eq!(zoomed.len(), sweep(49.0, 51.0, 0.01).len());
+        for &(f, c) in zoomed.iter() {
+            let expected = goertzel(&sine.data, sine.rate, f);
+            assert!((c - expected).norm() < 1e-3, "{} hz: {} vs {}", f, c, expected);
+        }
+
+        let direct = transform::zoom(&sine, 49.0, 0.01, zoomed.len());
+        for (&(_, c), z) in zoomed.iter().zip(direct.iter()) {
+            assert_eq!(c, *z);
+        }
+    }
+
+#[test]
+    fn test_analyze_peaks_only(){
+        // without full data the step size never changes the method,
+        // on either side of the zoom threshold or on the bin grid
+        let sine = util::sinewaves(1.0, 1000, &[(50.0,30.0)]);
+        let df = transform::bin_spacing(&sine);
+        let sweeps = [(49.0, 51.0, 0.01), (49.0, 51.0, 0.5), (49.0 * df, 51.0 * df, df)];
+        assert!(zoom_is_cheaper(sine.data.len(), sweep(49.0, 51.0, 0.01).len()));
+        assert!(!zoom_is_cheaper(sine.data.len(), sweep(49.0, 51.0, 0.5).len()));
+        assert!(on_bin_grid(&sine, 49.0 * df, df));
+
+        let data = sine.simplify();
+        for &(min, max, ss) in &sweeps {
+            let ft = analyze(&sine, min, max, ss);
+            assert_eq!(ft.len(), sweep(min, max, ss).len());
+            for &(f, c) in ft.iter() {
+                assert_eq!(c, calc_mean(graph_circle(&data, f)), "step {} at {} hz", ss, f);
+            }
+        }
+    }
+
+#[test]
+    fn test_analyze_f64(){
+        // 10 s of 8 khz, winding and fft paths both in double precision
+        let sine: Sample<f64> = util::sinewaves(10.0, 8000, &[(50.0,30.0)]);
+        assert_eq!(sine.data.len(), 80_000);
+
+        let polar = analyze_freq(&sine.points(), 50.0).to_polar();
+        assert!((polar.0 - 0.5).abs() < 1e-9, "{}", polar.0);
+        assert!((polar.1.to_degrees() - 30.0).abs() < 1e-6);
+
+        let full = AnalyzeOptions{ full_data: true, ..AnalyzeOptions::default() };
+        let ft = analyze_with(&sine, 49.9, 50.1, 0.1, &full);
+        let polar = ft[1].1.to_polar();
+        assert!((polar.0 - 0.5).abs() < 1e-9, "{}", polar.0);
+    }
+
+#[test]
+    fn test_analyze_threads(){
+        let sine = util::sinewaves(1.0, 1000, &[(5.0,90.0),(60.0,0.0)]);
+        for &full_data in &[false, true] {
+            let serial = AnalyzeOptions{ full_data, ..AnalyzeOptions::default() };
+            let threaded = AnalyzeOptions{ full_data, threads: 4, ..AnalyzeOptions::default() };
+            let a = analyze_with(&sine, 1.0, 30.0, 0.5, &serial);
+            let b = analyze_with(&sine, 1.0, 30.0, 0.5, &threaded);
+            assert_eq!(a.len(), b.len());
+            for (x, y) in a.iter().zip(b.iter()) {
+                assert_eq!(x.0, y.0);
+                assert_eq!(x.1, y.1);
+            }
+        }
+    }
+
+#[test]
+    fn test_analyze_fft_grid(){
+        let sine = util::sinewaves(1.0, 1024, &[(5.0,90.0),(60.0,0.0)]);
+        let full = AnalyzeOptions{ full_data: true, ..AnalyzeOptions::default() };
+        let ft = analyze_with(&sine, 1.0, 100.0, 1.0, &full);
+        assert_eq!(ft.len(), 100);
+
+        let points = sine.points();
+        for &(f, c) in ft.iter().filter(|&&(f, _)| f == 5.0 || f == 60.0) {
+            let winding = analyze_freq(&points, f);
+            assert!((c - winding).norm() < 1e-3);
+        }
+    }
+
+#[test]
+    fn test_analyze_fft_any_length(){
+        // 1000 is smooth, 1009 prime
+        for &rate in &[1000, 1009] {
+            let sine = util::sinewaves(1.0, rate, &[(5.0,90.0),(60.0,0.0)]);
+            let df = rate as f32 / sine.data.len() as f32;
+            let full = AnalyzeOptions{ full_data: true, ..AnalyzeOptions::default() };
+            let ft = analyze_with(&sine, 0.0, 100.0 * df, df, &full);
+
+            let points = sine.points();
+            for &(f, c) in ft.iter() {
+                let winding = analyze_freq(&points, f);
+                assert!((c - winding).norm() < 1e-3, "rate {} at {} hz", rate, f);
+            }
+        }
+    }
 }
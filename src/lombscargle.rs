@@ -0,0 +1,193 @@
+// generalized lomb-scargle periodogram for unevenly sampled data
+// (zechmeister & kürster 2009)
+// at every frequency a sinusoid plus offset is least squares fitted
+// to the points, and the power is the fraction of the data variance
+// the fit explains, so gaps and jitter do not bias the estimate the
+// way a plain mean over the points does
+
+use std::f64::consts::PI;
+use num_complex::Complex;
+use super::{Phasor, Real};
+
+pub struct Periodogram<T = f32> {
+    pub frequencies: Vec<T>,
+    // normalized power in 0.0..=1.0 per frequency
+    pub power: Vec<T>,
+    // fitted sinusoid per frequency in the graph_circle convention,
+    // a sine of amplitude a and phase p gives a/2 e^(ip)
+    pub phasors: Vec<Phasor<T>>,
+    // number of data points and their time span, seconds
+    pub len: usize,
+    pub span: T,
+}
+
+impl<T: Real> Periodogram<T> {
+    // index of the highest power
+    pub fn peak(&self) -> Option<usize> {
+        self.power.iter()
+            .enumerate()
+            .fold(None, |best: Option<(usize, T)>, (i, &p)| match best {
+                Some((_, q)) if q >= p => best,
+                _ => Some((i, p)),
+            })
+            .map(|(i, _)| i)
+    }
+
+    // probability that noise alone reaches power somewhere in the sweep
+    // a single frequency exceeds p with probability (1 - p)^((N-3)/2),
+    // and the sweep tests about span * bandwidth independent ones
+    pub fn false_alarm(&self, power: T) -> T {
+        if self.len <= 3 || self.frequencies.is_empty() {
+            return T::one();
+        }
+        let p = power.as_f64().clamp(0.0, 1.0);
+        let single = (1.0 - p).powf((self.len - 3) as f64 / 2.0);
+
+        let first = self.frequencies[0].as_f64();
+        let last = self.frequencies[self.frequencies.len() - 1].as_f64();
+        let independent = (self.span.as_f64() * (last - first).abs())
+            .min(self.frequencies.len() as f64)
+            .max(1.0);
+        T::from_f64(1.0 - (1.0 - single).powf(independent))
+    }
+
+    // indices of local power maxima whose false alarm probability
+    // is below fap, strongest first
+    pub fn peaks(&self, fap: T) -> Vec<usize> {
+        let p = &self.power;
+        let mut peaks: Vec<usize> = (0..p.len())
+            .filter(|&i| (i == 0 || p[i] > p[i - 1]) && (i + 1 == p.len() || p[i] >= p[i + 1]))
+            .filter(|&i| p[i].is_finite() && self.false_alarm(p[i]) < fap)
+            .collect();
+        peaks.sort_by(|&a, &b| p[b].as_f64().total_cmp(&p[a].as_f64()));
+        peaks
+    }
+}
+
+// periodogram of (time, amplitude) points over min..=max in steps of ss hz
+pub fn lomb_scargle<T: Real>(data: &[(T,T)], min: T, max: T, ss: T) -> Periodogram<T> {
+    let points: Vec<(f64, f64)> = data.iter().map(|&(t, y)| (t.as_f64(), y.as_f64())).collect();
+    let n = points.len().max(1) as f64;
+
+    // sums run in f64, times are shifted to the first point so
+    // long logs keep their phase precision
+    let t0 = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let t1 = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+    let mean = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let variance = points.iter().map(|p| (p.1 - mean) * (p.1 - mean)).sum::<f64>() / n;
+
+    let mut frequencies = vec![];
+    let mut power = vec![];
+    let mut phasors = vec![];
+    let mut f = min;
+    while f <= max {
+        let w = 2.0 * PI * f.as_f64();
+        // weighted sums with equal weights 1/n
+        let (mut c, mut s, mut yc, mut ys, mut cc, mut ss2, mut cs) = (0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        for &(t, y) in &points {
+            let (sin, cos) = (w * (t - t0)).sin_cos();
+            c += cos;
+            s += sin;
+            yc += y * cos;
+            ys += y * sin;
+            cc += cos * cos;
+            ss2 += sin * sin;
+            cs += cos * sin;
+        }
+        let (c, s) = (c / n, s / n);
+        // covariances around the means
+        let yc = yc / n - mean * c;
+        let ys = ys / n - mean * s;
+        let cc = cc / n - c * c;
+        let ss2 = ss2 / n - s * s;
+        let cs = cs / n - c * s;
+        let d = cc * ss2 - cs * cs;
+
+        let (p, fit) = if d > 1e-12 && variance > 0.0 {
+            let p = (ss2 * yc * yc + cc * ys * ys - 2.0 * cs * yc * ys) / (variance * d);
+            // y = a cos(wt) + b sin(wt) + offset
+            //   = hypot(a, b) sin(wt + atan2(a, b)) + offset
+            let a = (yc * ss2 - ys * cs) / d;
+            let b = (ys * cc - yc * cs) / d;
+            // refer the phase back from t0 to t = 0
+            let phase = a.atan2(b) - w * t0;
+            (p, Complex::from_polar(&(0.5 * a.hypot(b)), &phase))
+        } else {
+            (0.0, Complex{ re: 0.0, im: 0.0 })
+        };
+
+        frequencies.push(f);
+        power.push(T::from_f64(p.clamp(0.0, 1.0)));
+        phasors.push(Phasor{
+            frequency: f,
+            complex: Complex{ re: T::from_f64(fit.re), im: T::from_f64(fit.im) },
+        });
+        f += ss;
+    }
+
+    Periodogram {
+        frequencies,
+        power,
+        phasors,
+        len: points.len(),
+        span: T::from_f64(if points.is_empty() { 0.0 } else { t1 - t0 }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+use super::*;
+use tests::noise;
+
+// points of amplitude * sin(2πft + phase) + offset at jittered
+// times with a gap in the middle
+fn uneven(f: f32, phase: f32, amplitude: f32, offset: f32, noise_level: f32) -> Vec<(f32, f32)> {
+    let jitter = noise(400, 7);
+    let noise = noise(400, 11);
+    (0..400)
+        .filter(|&i| !(150..250).contains(&i))
+        .map(|i| {
+            let t = (i as f32 + 0.4 * jitter[i]) / 100.0;
+            let y = amplitude * (2.0 * std::f32::consts::PI * f * t + phase).sin() + offset + noise_level * noise[i];
+            (t, y)
+        })
+        .collect()
+}
+
+#[test]
+    fn test_lomb_scargle_fit(){
+        let data = uneven(7.3, 0.6, 1.5, 2.0, 0.0);
+        let gls = lomb_scargle(&data, 0.5, 40.0, 0.05);
+        let peak = gls.peak().unwrap();
+
+        assert!((gls.frequencies[peak] - 7.3).abs() < 0.03);
+        assert!(gls.power[peak] > 0.99);
+        let (r, theta) = gls.phasors[peak].complex.to_polar();
+        assert!((r - 0.75).abs() < 0.02);
+        assert!((theta - 0.6).abs() < 0.05);
+        assert_eq!(gls.len, 300);
+        assert!((gls.span - 3.99).abs() < 0.01);
+    }
+
+#[test]
+    fn test_lomb_scargle_false_alarm(){
+        let signal = lomb_scargle(&uneven(7.3, 0.0, 0.5, 0.0, 1.0), 0.5, 40.0, 0.05);
+        let peaks = signal.peaks(0.01);
+        assert!(!peaks.is_empty());
+        assert!((signal.frequencies[peaks[0]] - 7.3).abs() < 0.05);
+        assert!(signal.false_alarm(signal.power[peaks[0]]) < 1e-6);
+
+        // noise alone has no significant peaks
+        let quiet = lomb_scargle(&uneven(7.3, 0.0, 0.0, 0.0, 1.0), 0.5, 40.0, 0.05);
+        assert!(quiet.peaks(0.01).is_empty());
+        assert!(quiet.false_alarm(quiet.power[quiet.peak().unwrap()]) > 0.01);
+    }
+
+#[test]
+    fn test_lomb_scargle_nan(){
+        let mut data = uneven(7.3, 0.0, 0.5, 0.0, 1.0);
+        data[5].1 = f32::NAN;
+        let gls = lomb_scargle(&data, 0.5, 40.0, 0.05);
+        assert!(gls.peaks(0.01).is_empty());
+    }
+}
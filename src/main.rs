@@ -7,9 +7,10 @@ extern crate tui;
 extern crate termion;
 extern crate num_complex;
 extern crate hound;
-use std::f32::consts::PI;
+extern crate png;
 
 mod plot;
+mod image;
 
 use termion::input::TermRead;
 use tui::style::{Color};
@@ -38,7 +39,7 @@ fn load_sample_from_file(file: &str) -> fft::Sample {
                     .step_by(spec.channels as usize)
                     .map(|s| {
                         // convert to -1.0..1.0 f32
-                        (s.unwrap() as f32 / std::i32::MAX as f32 * 10000.0) as f32
+                        s.unwrap() as f32 / i32::MAX as f32 * 10000.0
                     })
                 .collect(),
                 rate: spec.sample_rate as usize
@@ -47,6 +48,70 @@ fn load_sample_from_file(file: &str) -> fft::Sample {
     }
 }
 
+// 2d spectrum of a grayscale image, reports its strongest periodicity
+// and optionally writes the centered log-magnitude spectrum as an image
+fn analyze_image(file: &str, spectrum_out: &Option<String>) {
+    let img = match image::load(file) {
+        Ok(img) => img,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let (rows, cols) = (img.height, img.width);
+    println!("{}: {}x{}", file, cols, rows);
+
+    let mut spectrum = fft::fft2d::to_complex(&img.data);
+    fft::fft2d::fft2(&mut spectrum, rows, cols);
+
+    // strongest non-dc component, in cycles per image
+    // along x and y, the upper halves alias to negative
+    // of the two conjugate peaks the first one found is kept
+    let peak = (2..spectrum.len())
+        .fold(1, |best, i| if spectrum[i].norm() > spectrum[best].norm() { i } else { best });
+    if spectrum.len() > 1 && spectrum[peak].norm() > 1e-3 {
+        let signed = |k: usize, n: usize| if k > n / 2 { k as isize - n as isize } else { k as isize };
+        let (fy, fx) = (signed(peak / cols, rows), signed(peak % cols, cols));
+        // wavelength across the stripes, 1 / |spatial frequency|
+        let period = 1.0 / (fx as f32 / cols as f32).hypot(fy as f32 / rows as f32);
+        println!("strongest periodicity: {} x {} cycles per image, period {:.2} px", fx, fy, period);
+    } else {
+        println!("no periodicity, image is flat");
+    }
+
+    if let Some(ref out) = *spectrum_out {
+        let shifted = fft::fft2d::shift(&spectrum, rows, cols);
+        let data = fft::fft2d::log_magnitude(&shifted);
+        match image::save(out, &image::Image{ data, width: cols, height: rows }) {
+            Ok(()) => println!("log-magnitude spectrum written to {}", out),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+// welch psd table for bins between min and max
+fn print_welch(sample: &fft::Sample, options: &fft::psd::WelchOptions, min: f32, max: f32) {
+    let psd = match fft::psd::welch(sample, options) {
+        Ok(psd) => psd,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("Welch PSD: {} point {} segments, overlap {}, {} detrend",
+             psd.len, options.window, psd.overlap, options.detrend);
+    println!("{} segments, resolution {} hz", psd.segments, psd.spacing());
+    println!("{:>12} {:>14} {:>14}", "hz", "V²/Hz", "V²");
+    for (k, &f) in psd.frequencies.iter().enumerate() {
+        if f >= min && f <= max {
+            println!("{:>12.3} {:>14.6e} {:>14.6e}", f, psd.density[k], psd.spectrum[k]);
+        }
+    }
+}
+
 fn main() {
     // sine sample generation args
     let mut gen_t = 2.0; // time secs
@@ -59,7 +124,16 @@ fn main() {
     let mut ft_ss = 1.0;    // stepsize, hz
     let mut ft_res: Option<f32> = None;
     let mut input_file = String::new();
+    let mut image_file = String::new();
+    let mut spectrum_out: Option<String> = None;
+    let mut welch = false;
+    let mut welch_options = fft::psd::WelchOptions::default();
+    let mut window: Option<fft::Window> = None;
     let mut noplot = false;
+    let mut ft_options = fft::AnalyzeOptions{
+        threads: fft::parallel::available_threads(),
+        ..fft::AnalyzeOptions::default()
+    };
     {
         // parse arguments
         let mut ap = ArgumentParser::new();
@@ -90,27 +164,71 @@ fn main() {
         ap.refer(&mut ft_res)
             .add_option(&["--res", "--resolution"], StoreOption,
                         "FT analysis resolution, overrides stepsize if given");
+        ap.refer(&mut ft_options.full_data)
+            .add_option(&["--full"], StoreTrue,
+                        "FT analysis over full sample data instead of peaks only, enables the FFT, zoom FFT and Goertzel paths");
+        ap.refer(&mut window)
+            .add_option(&["--window"], StoreOption,
+                        "FT analysis and Welch segment window: rectangular, hann, hamming, blackman, \
+                        blackman-harris, flattop, kaiser[:beta] or tukey[:alpha]");
+        ap.refer(&mut ft_options.threads)
+            .add_option(&["--threads"], Store,
+                        "FT analysis threads, defaults to all cores");
+        ap.refer(&mut welch)
+            .add_option(&["--welch"], StoreTrue,
+                        "Print the Welch power spectral density between min and max instead of the FT sweep");
+        ap.refer(&mut welch_options.segment)
+            .add_option(&["--segment"], Store,
+                        "Welch segment length in samples");
+        ap.refer(&mut welch_options.overlap)
+            .add_option(&["--overlap"], Store,
+                        "Welch segment overlap in samples");
+        ap.refer(&mut welch_options.detrend)
+            .add_option(&["--detrend"], Store,
+                        "Welch segment detrending: none, mean or linear");
         ap.refer(&mut input_file)
             .add_option(&["--input", "-i"], Store,
                         "Input .wav file");
+        ap.refer(&mut image_file)
+            .add_option(&["--image"], Store,
+                        "Input grayscale .pgm or .png image for 2D analysis");
+        ap.refer(&mut spectrum_out)
+            .add_option(&["--spectrum-out"], StoreOption,
+                        "Write the image's log-magnitude spectrum to this .pgm or .png file");
         ap.parse_args_or_exit();
     }
 
+    // the FT sweep defaults to rectangular, welch segments to hann
+    if let Some(window) = window {
+        ft_options.window = window;
+        welch_options.window = window;
+    }
+
+    if !image_file.is_empty() {
+        analyze_image(&image_file, &spectrum_out);
+        return;
+    }
+
     // Override stepsize if using point resolution
-    if ft_res.is_some() {
+    if let Some(res) = ft_res {
         // calculate stepsize
-        ft_ss = (ft_max - ft_min) / ft_res.unwrap();
+        ft_ss = (ft_max - ft_min) / res;
     }
 
     // create sample
     let mut sample = fft::Sample{ data: vec![], rate: gen_sf };
-    if input_file.len() > 0 {
+    if !input_file.is_empty() {
         sample = load_sample_from_file(&input_file);
     }
-    else if gen_frequencies.len() > 0 {
+    else if !gen_frequencies.is_empty() {
         sample = util::sinewaves(gen_t, sample.rate, &util::parse_freq_phase_pairs(gen_frequencies));
     }
 
+    if welch && !sample.is_empty() {
+        print_welch(&sample, &welch_options, ft_min, ft_max);
+        return;
+    }
+
     // run analysis
     // plots realtime text graph
     if !sample.is_empty() {
@@ -118,7 +236,7 @@ fn main() {
         let mut f = ft_min;
 
         if noplot {
-            let analysis = fft::analyze(&sample, ft_min, ft_max, ft_ss);
+            fft::analyze_with(&sample, ft_min, ft_max, ft_ss, &ft_options);
         } 
 
         else {
@@ -127,19 +245,24 @@ fn main() {
             term.hide_cursor().unwrap();
             term.clear().unwrap();
             let mut peaks: Vec<usize> = vec![];
-            let data = sample.simplify();
+            let data = ft_options.points(&sample);
+            let windowed = ft_options.window.apply_sample(&sample);
 
             // process graphs
             while f <= ft_max {
                 plot::draw_waveform(&mut term, &sample, Color::White);
-                if input_file.len() > 0 {
+                if !input_file.is_empty() {
                     term.draw().unwrap();
                     break;
                 }
                 plot::draw_circle(&mut term, &fft::graph_circle(&data,f));
                 ft_data.push(fft::Phasor{ 
                     frequency: f, 
-                    complex: fft::analyze_freq(&data,f)
+                    complex: if ft_options.full_data {
+                        fft::goertzel(&windowed.data, windowed.rate, f)
+                    } else {
+                        fft::analyze_freq(&data,f)
+                    }
                 });
                 if ft_data.len() > 3 {
                     let peak = fft::max(&ft_data[(ft_data.len() - 3)..]);
@@ -181,10 +304,9 @@ fn main() {
                     ];
 
                     for (idx,p) in peak_phasors.into_iter().enumerate() {
-                        // Draw phasor waveform
-                        let (r,theta) = p.complex.to_polar();
-                        let degrees = theta * 180.0 / PI;
-                        let sine = util::sinewave(p.frequency,degrees,sample.time(),sample.rate,r);
+                        // Draw phasor waveform, resynthesized over the sample's length
+                        let phasor = fft::Phasor{ frequency: p.frequency, complex: p.complex };
+                        let sine = fft::real::from_phasors(&[phasor], sample.data.len(), sample.rate);
                         plot::draw_waveform(&mut term, &sine, colors[idx % colors.len()]);
                     }
                 }
@@ -192,11 +314,7 @@ fn main() {
             }
 
             // stop for key events
-            for c in stdin().keys() {
-                match c.unwrap() {
-                    _ => break
-                }
-            }
+            stdin().keys().next();
 
             // clean up
             term.show_cursor().unwrap();
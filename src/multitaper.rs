@@ -0,0 +1,417 @@
+// thomson multitaper spectral estimation
+// the sample is windowed by several orthogonal discrete prolate
+// spheroidal sequences (dpss), each maximally concentrated in a band
+// of ±W around its center, and their periodograms averaged
+// unlike welch no data is thrown away, the variance drops with the
+// number of tapers at a resolution of 2W
+
+use std::f64::consts::PI;
+use num_complex::Complex;
+use super::{Sample, Real};
+use real::RealFftPlan;
+use transform::FftPlan;
+
+// dpss tapers of one length and time-bandwidth product
+pub struct Tapers {
+    // unit energy tapers, most concentrated first
+    pub tapers: Vec<Vec<f64>>,
+    // fraction of each taper's energy inside the band ±W
+    pub concentrations: Vec<f64>,
+}
+
+// the first count dpss of len points for time-bandwidth product
+// nw, where W = nw / len in cycles per sample
+// they are the eigenvectors of a symmetric tridiagonal matrix that
+// commutes with the band concentration problem (slepian), found by
+// bisection on the eigenvalues and inverse iteration
+// symmetric tapers sum positive, antisymmetric ones start positive
+pub fn dpss(len: usize, nw: f64, count: usize) -> Tapers {
+    assert!(count <= len, "{} tapers of {} points requested", count, len);
+    assert!(nw > 0.0 && nw < len as f64 / 2.0, "time-bandwidth product {} out of range", nw);
+    let n = len as f64;
+    let w = nw / n;
+
+    let diag: Vec<f64> = (0..len)
+        .map(|i| {
+            let c = (n - 1.0 - 2.0 * i as f64) / 2.0;
+            c * c * (2.0 * PI * w).cos()
+        })
+        .collect();
+    // off[i] couples i - 1 and i, off[0] unused
+    let off: Vec<f64> = (0..len).map(|i| i as f64 * (n - i as f64) / 2.0).collect();
+
+    let tapers: Vec<Vec<f64>> = (0..count)
+        .map(|k| {
+            let lambda = tridiagonal_eigenvalue(&diag, &off, len - 1 - k);
+            let mut v = inverse_iteration(&diag, &off, lambda);
+            let sign = if k % 2 == 0 {
+                v.iter().sum::<f64>()
+            } else {
+                v.iter().enumerate().map(|(i, x)| (n - 1.0 - 2.0 * i as f64) * x).sum::<f64>()
+            };
+            if sign < 0.0 {
+                for x in v.iter_mut() {
+                    *x = -*x;
+                }
+            }
+            v
+        })
+        .collect();
+
+    let concentrations = tapers.iter().map(|v| concentration(v, w)).collect();
+    Tapers { tapers, concentrations }
+}
+
+// number of eigenvalues below x (sturm sequence count)
+fn eigenvalues_below(diag: &[f64], off: &[f64], x: f64) -> usize {
+    let mut count = 0;
+    let mut q = 1.0;
+    for i in 0..diag.len() {
+        let coupling = if i == 0 { 0.0 } else { off[i] * off[i] / q };
+        q = diag[i] - x - coupling;
+        if q == 0.0 {
+            q = f64::EPSILON * (diag[i].abs() + 1.0);
+        }
+        if q < 0.0 {
+            count += 1;
+        }
+    }
+    count
+}
+
+// index:th smallest eigenvalue by bisection within gershgorin bounds
+fn tridiagonal_eigenvalue(diag: &[f64], off: &[f64], index: usize) -> f64 {
+    let radius = |i: usize| off[i].abs() + off.get(i + 1).map_or(0.0, |e| e.abs());
+    let (mut lo, mut hi) = (0..diag.len())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), i| {
+            (lo.min(diag[i] - radius(i)), hi.max(diag[i] + radius(i)))
+        });
+    for _ in 0..200 {
+        let mid = 0.5 * (lo + hi);
+        if mid <= lo || mid >= hi {
+            break;
+        }
+        if eigenvalues_below(diag, off, mid) <= index {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+// eigenvector for an eigenvalue, unit length
+// solves (T - λI) y = x a few times, each solve amplifies the
+// eigenvector's component by the inverse distance to λ
+fn inverse_iteration(diag: &[f64], off: &[f64], lambda: f64) -> Vec<f64> {
+    let len = diag.len();
+    // start away from symmetry so antisymmetric vectors are reached
+    let mut x: Vec<f64> = (0..len).map(|i| 1.0 + i as f64 / len as f64).collect();
+    let mut c = vec![0.0; len];
+    for _ in 0..3 {
+        // thomas algorithm, guarding exact zero pivots
+        let mut pivot = diag[0] - lambda;
+        for i in 0..len {
+            if i > 0 {
+                c[i - 1] = off[i] / pivot;
+                pivot = diag[i] - lambda - off[i] * c[i - 1];
+                x[i] -= off[i] * x[i - 1];
+            }
+            if pivot == 0.0 {
+                pivot = f64::EPSILON * (diag[i].abs() + 1.0);
+            }
+            x[i] /= pivot;
+        }
+        for i in (0..len - 1).rev() {
+            x[i] -= c[i] * x[i + 1];
+        }
+        let norm = x.iter().map(|v| v * v).sum::<f64>().sqrt();
+        for v in x.iter_mut() {
+            *v /= norm;
+        }
+    }
+    x
+}
+
+// energy of unit taper v inside ±w cycles per sample,
+// v' A v with A[n][m] = sin(2πw(n-m)) / (π(n-m)), through the
+// autocorrelation of v
+fn concentration(v: &[f64], w: f64) -> f64 {
+    let len = v.len();
+    let size = (2 * len).next_power_of_two();
+    let plan = FftPlan::new(size);
+    let mut spectrum: Vec<Complex<f64>> = (0..size)
+        .map(|i| Complex{ re: if i < len { v[i] } else { 0.0 }, im: 0.0 })
+        .collect();
+    plan.process(&mut spectrum);
+    for c in spectrum.iter_mut() {
+        *c = Complex{ re: c.norm_sqr(), im: 0.0 };
+    }
+    plan.inverse(&mut spectrum);
+
+    2.0 * w * spectrum[0].re + 2.0 * (1..len)
+        .map(|l| spectrum[l].re * (2.0 * PI * w * l as f64).sin() / (PI * l as f64))
+        .sum::<f64>()
+}
+
+// Options for multitaper estimation
+#[derive(Clone)]
+pub struct MultitaperOptions {
+    // time-bandwidth product, resolution is 2 * nw / Sample::time() hz
+    // limited to (len - 1) / 2 for short samples
+    pub nw: f64,
+    // tapers to average, 0 uses the 2nw - 1 well concentrated ones
+    pub tapers: usize,
+    // weight tapers per bin by their expected leakage (thomson)
+    // instead of by concentration alone
+    pub adaptive: bool,
+    // confidence level of the reported intervals, eg. 0.95,
+    // strictly between 0 and 1
+    pub confidence: f64,
+}
+
+impl Default for MultitaperOptions {
+    fn default() -> MultitaperOptions {
+        MultitaperOptions { nw: 4.0, tapers: 0, adaptive: true, confidence: 0.95 }
+    }
+}
+
+// one-sided multitaper density estimate
+pub struct MultitaperPsd<T = f32> {
+    // bin frequencies 0..=rate/2, hz
+    pub frequencies: Vec<T>,
+    // power spectral density, V²/Hz
+    pub density: Vec<T>,
+    // confidence interval of the density per bin
+    pub lower: Vec<T>,
+    pub upper: Vec<T>,
+    // equivalent degrees of freedom per bin, 2 * tapers when
+    // not adaptive
+    pub dof: Vec<T>,
+    // time-bandwidth product and taper count actually used
+    pub nw: f64,
+    pub tapers: usize,
+}
+
+// multitaper estimate of sample's power spectral density
+pub fn multitaper<T: Real>(sample: &Sample<T>, options: &MultitaperOptions) -> MultitaperPsd<T> {
+    let len = sample.data.len();
+    assert!(len > 1, "multitaper needs at least 2 points");
+    assert!(options.confidence > 0.0 && options.confidence < 1.0,
+            "confidence level {} out of range", options.confidence);
+    // dpss needs nw < len / 2, which the default 4 exceeds below 9 points
+    let nw = options.nw.min((len - 1) as f64 / 2.0);
+    let count = if options.tapers == 0 {
+        ((2.0 * nw).floor() as usize).saturating_sub(1).max(1)
+    } else {
+        options.tapers
+    };
+    let tapers = dpss(len, nw, count.min(len));
+    let count = tapers.tapers.len();
+    let bins = len / 2 + 1;
+    let rate = sample.rate as f64;
+
+    // mean removed, as the dc leakage of the tapers would otherwise
+    // swamp the low bins
+    let data: Vec<f64> = sample.data.iter().map(|x| x.as_f64()).collect();
+    let mean = data.iter().sum::<f64>() / len as f64;
+    let variance = data.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / len as f64;
+
+    // eigenspectra |sum v_k x e|² per taper
+    let plan = RealFftPlan::<f64>::new(len);
+    let eigen: Vec<Vec<f64>> = tapers.tapers.iter()
+        .map(|v| {
+            let windowed: Vec<f64> = data.iter().zip(v.iter()).map(|(x, w)| (x - mean) * w).collect();
+            plan.forward(&windowed).iter().map(|c| c.norm_sqr()).collect()
+        })
+        .collect();
+    let lambda = &tapers.concentrations;
+
+    let mut density = vec![0.0; bins];
+    let mut dof = vec![2.0 * count as f64; bins];
+    for k in 0..bins {
+        if !options.adaptive || count < 2 {
+            let total: f64 = lambda.iter().sum();
+            density[k] = eigen.iter().zip(lambda.iter()).map(|(s, l)| l * s[k]).sum::<f64>() / total;
+            continue;
+        }
+        // iterate s = sum d² s_k / sum d² with
+        // d_k = sqrt(λ_k) s / (λ_k s + (1 - λ_k) σ²)
+        let mut s = 0.5 * (eigen[0][k] + eigen[1][k]);
+        let mut weights = vec![1.0; count];
+        for _ in 0..100 {
+            for (d, &l) in weights.iter_mut().zip(lambda.iter()) {
+                *d = l.sqrt() * s / (l * s + (1.0 - l) * variance);
+            }
+            let (num, den) = weights.iter().zip(eigen.iter())
+                .fold((0.0, 0.0), |acc, (d, e)| (acc.0 + d * d * e[k], acc.1 + d * d));
+            let next = if den > 0.0 { num / den } else { 0.0 };
+            let done = (next - s).abs() <= 1e-10 * s.abs();
+            s = next;
+            if done {
+                break;
+            }
+        }
+        density[k] = s;
+        let (d2, d4) = weights.iter().fold((0.0, 0.0), |acc, d| (acc.0 + d * d, acc.1 + d * d * d * d));
+        if d4 > 0.0 {
+            dof[k] = 2.0 * d2 * d2 / d4;
+        }
+    }
+
+    // s_k / fs is a two-sided density for unit energy tapers,
+    // doubled off dc and nyquist
+    let one_sided = |k: usize| if k == 0 || (len.is_multiple_of(2) && k == len / 2) { 1.0 } else { 2.0 };
+    let density: Vec<f64> = density.iter().enumerate().map(|(k, s)| s * one_sided(k) / rate).collect();
+
+    // ν s / χ²_ν has the true density as its mean
+    let alpha = 1.0 - options.confidence;
+    let bound = |k: usize, p: f64| density[k] * dof[k] / chi_square_quantile(p, dof[k]);
+
+    MultitaperPsd {
+        frequencies: (0..bins).map(|k| T::from_f64(k as f64 * rate / len as f64)).collect(),
+        density: density.iter().map(|&d| T::from_f64(d)).collect(),
+        lower: (0..bins).map(|k| T::from_f64(bound(k, 1.0 - alpha / 2.0))).collect(),
+        upper: (0..bins).map(|k| T::from_f64(bound(k, alpha / 2.0))).collect(),
+        dof: dof.iter().map(|&v| T::from_f64(v)).collect(),
+        nw,
+        tapers: count,
+    }
+}
+
+// p quantile of the standard normal distribution
+// (abramowitz & stegun 26.2.23, error below 4.5e-4)
+fn normal_quantile(p: f64) -> f64 {
+    if p > 0.5 {
+        return -normal_quantile(1.0 - p);
+    }
+    let t = (-2.0 * p.ln()).sqrt();
+    -(t - (2.515517 + 0.802853 * t + 0.010328 * t * t)
+        / (1.0 + 1.432788 * t + 0.189269 * t * t + 0.001308 * t * t * t))
+}
+
+// p quantile of the chi-square distribution with dof degrees of
+// freedom (wilson-hilferty)
+fn chi_square_quantile(p: f64, dof: f64) -> f64 {
+    let a = 2.0 / (9.0 * dof);
+    let cube = 1.0 - a + normal_quantile(p) * a.sqrt();
+    (dof * cube * cube * cube).max(f64::MIN_POSITIVE)
+}
+
+#[cfg(test)]
+mod tests {
+use super::*;
+use tests::noise;
+use util;
+
+#[test]
+    fn test_dpss_eigenproblem(){
+        let (len, nw) = (64, 3.0);
+        let w = nw / len as f64;
+        let tapers = dpss(len, nw, 6);
+        assert_eq!(tapers.tapers.len(), 6);
+
+        for (k, v) in tapers.tapers.iter().enumerate() {
+            // A v = λ v for the band concentration matrix
+            let lambda = tapers.concentrations[k];
+            for n in 0..len {
+                let av: f64 = (0..len)
+                    .map(|m| {
+                        let a = if n == m { 2.0 * w } else {
+                            let d = n as f64 - m as f64;
+                            (2.0 * PI * w * d).sin() / (PI * d)
+                        };
+                        a * v[m]
+                    })
+                    .sum();
+                assert!((av - lambda * v[n]).abs() < 1e-9, "taper {}", k);
+            }
+            // orthonormal
+            for (j, u) in tapers.tapers.iter().enumerate() {
+                let dot: f64 = u.iter().zip(v.iter()).map(|(a, b)| a * b).sum();
+                assert!((dot - if j == k { 1.0 } else { 0.0 }).abs() < 1e-9);
+            }
+            // alternately symmetric and antisymmetric, signs fixed
+            let parity = if k % 2 == 0 { 1.0 } else { -1.0 };
+            assert!((0..len).all(|i| (v[i] - parity * v[len - 1 - i]).abs() < 1e-9));
+            let lead: f64 = if k % 2 == 0 { v.iter().sum() } else { v[..len / 2].iter().sum() };
+            assert!(lead > 0.0, "taper {}", k);
+        }
+        // the first 2nw - 1 are well concentrated, then it drops
+        assert!(tapers.concentrations[..5].iter().all(|&l| l > 0.9));
+        assert!(tapers.concentrations[5] < 0.75);
+        assert!(tapers.concentrations.windows(2).all(|p| p[0] > p[1]));
+    }
+
+#[test]
+    fn test_multitaper_noise(){
+        // uniform noise in -1..1 has a one-sided density of 2/3 / rate
+        let sample = Sample{ data: noise(4096, 5), rate: 1000 };
+        let truth = 2.0 / 3.0 / 1000.0;
+        for &adaptive in &[false, true] {
+            let psd = multitaper(&sample, &MultitaperOptions{ adaptive, ..MultitaperOptions::default() });
+            assert_eq!(psd.frequencies.len(), 2049);
+            let interior = 1..psd.density.len() - 1;
+            let mean = interior.clone().map(|k| psd.density[k]).sum::<f32>() / (psd.density.len() - 2) as f32;
+            assert!((mean - truth).abs() < 0.05 * truth, "adaptive {}", adaptive);
+
+            // the 95% intervals cover the true level about 95% of the time
+            let covered = interior.clone()
+                .filter(|&k| psd.lower[k] <= truth && truth <= psd.upper[k])
+                .count() as f32 / (psd.density.len() - 2) as f32;
+            assert!(covered > 0.9 && covered < 0.99, "adaptive {} coverage {}", adaptive, covered);
+            assert!(interior.clone().all(|k| psd.dof[k] <= 14.0 + 1e-3 && psd.dof[k] > 2.0));
+        }
+    }
+
+#[test]
+    fn test_multitaper_adaptive_leakage(){
+        // a loud tone over a faint floor leaks through the poorly
+        // concentrated tapers unless they are weighted down
+        let mut sample: Sample = util::sinewave(100.0, 0.0, 1.0, 1000, 1.0);
+        // 1000 points put the bins on whole hz
+        sample.data.truncate(1000);
+        for (x, n) in sample.data.iter_mut().zip(noise(1000, 9)) {
+            *x += 1e-4 * n;
+        }
+        let options = MultitaperOptions{ nw: 4.0, tapers: 8, ..MultitaperOptions::default() };
+        let adaptive = multitaper(&sample, &options);
+        let fixed = multitaper(&sample, &MultitaperOptions{ adaptive: false, ..options });
+
+        // tone power a²/2 integrates within the band
+        let band = |psd: &MultitaperPsd| (90..=110).map(|k| psd.density[k]).sum::<f32>();
+        assert!((band(&adaptive) - 0.5).abs() < 0.02);
+        // just outside the ±4 hz band the adaptive floor is far lower
+        assert!(adaptive.density[110] * 10.0 < fixed.density[110]);
+    }
+
+#[test]
+    fn test_multitaper_short_record(){
+        // the default nw = 4 does not fit records of 8 points or fewer
+        for len in 2..=9 {
+            let sample = Sample{ data: noise(len, 5), rate: 100 };
+            let psd = multitaper(&sample, &MultitaperOptions::default());
+            assert_eq!(psd.density.len(), len / 2 + 1);
+            assert!(psd.nw < len as f64 / 2.0, "nw {} for {} points", psd.nw, len);
+            assert!(psd.tapers >= 1 && psd.tapers <= len);
+            for k in 0..psd.density.len() {
+                assert!(psd.density[k].is_finite() && psd.density[k] >= 0.0, "{} points bin {}", len, k);
+                assert!(psd.lower[k] <= psd.density[k] && psd.density[k] <= psd.upper[k]);
+            }
+        }
+        let psd = multitaper(&Sample{ data: noise(64, 5), rate: 100 }, &MultitaperOptions::default());
+        assert_eq!((psd.nw, psd.tapers), (4.0, 7));
+    }
+
+#[test]
+    fn test_multitaper_confidence_range(){
+        let sample = Sample{ data: noise(64, 5), rate: 100 };
+        for &confidence in &[0.0, 1.0, -0.5, 1.5, f64::NAN] {
+            let options = MultitaperOptions{ confidence, ..MultitaperOptions::default() };
+            let result = std::panic::catch_unwind(|| multitaper(&sample, &options));
+            assert!(result.is_err(), "confidence {} accepted", confidence);
+        }
+        let psd = multitaper(&sample, &MultitaperOptions{ confidence: 0.5, ..MultitaperOptions::default() });
+        assert!((1..32).all(|k| psd.lower[k] < psd.density[k] && psd.density[k] < psd.upper[k]));
+    }
+}
@@ -0,0 +1,48 @@
+// scoped thread fan-out for independent work items
+
+use std::thread;
+
+// number of threads the machine can run at once
+pub fn available_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+// maps f over items on up to threads scoped threads
+// items are split into contiguous chunks, one per thread, and the
+// results joined back in input order, so output is the same as a
+// serial map whatever the thread count
+// 0 or 1 threads runs serially on the calling thread
+pub fn map<I, R, F>(items: &[I], threads: usize, f: F) -> Vec<R>
+    where I: Sync, R: Send, F: Fn(&I) -> R + Sync
+{
+    let threads = threads.min(items.len());
+    if threads <= 1 {
+        return items.iter().map(&f).collect();
+    }
+
+    let chunk = items.len().div_ceil(threads);
+    let f = &f;
+    thread::scope(|scope| {
+        let handles: Vec<_> = items.chunks(chunk)
+            .map(|part| scope.spawn(move || part.iter().map(f).collect::<Vec<R>>()))
+            .collect();
+        handles.into_iter()
+            .flat_map(|h| h.join().expect("worker thread panicked"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+use super::*;
+
+#[test]
+    fn test_map_ordered(){
+        let items: Vec<usize> = (0..1001).collect();
+        let serial = map(&items, 1, |&i| i * i);
+        for &threads in &[0, 2, 3, 7, 32, 5000] {
+            assert_eq!(map(&items, threads, |&i| i * i), serial);
+        }
+        assert!(map(&[] as &[usize], 4, |&i| i).is_empty());
+    }
+}
@@ -0,0 +1,559 @@
+// parametric spectral estimation
+// instead of transforming the data a model is fitted to it, an
+// autoregressive filter (burg) or a sum of sinusoids in noise
+// (music, esprit), which resolves tones closer together than the
+// fourier resolution 1 / Sample::time() of short records
+
+use std::f64::consts::PI;
+use num_complex::Complex;
+use super::{Sample, Phasor, Real};
+
+// autoregressive model x[n] = -sum a[k] x[n-k] + e[n], k = 1..=order
+pub struct ArModel<T = f32> {
+    // a[1..=order]
+    pub coefficients: Vec<T>,
+    // variance of the driving white noise e
+    pub variance: T,
+    pub rate: usize,
+}
+
+impl<T: Real> ArModel<T> {
+    pub fn order(&self) -> usize {
+        self.coefficients.len()
+    }
+
+    // one-sided power spectral density at f, V²/Hz
+    // 2 σ² / (fs |1 + sum a[k] e^(-2πifk/fs)|²)
+    pub fn density(&self, f: T) -> T {
+        let w = 2.0 * PI * f.as_f64() / self.rate as f64;
+        let response = self.coefficients.iter()
+            .enumerate()
+            .fold(Complex{ re: 1.0, im: 0.0 }, |acc, (k, a)| {
+                acc + Complex::from_polar(&a.as_f64(), &(-w * (k + 1) as f64))
+            });
+        T::from_f64(2.0 * self.variance.as_f64() / (self.rate as f64 * response.norm_sqr()))
+    }
+
+    // density over a min..max sweep as (frequency, density)
+    pub fn sweep(&self, min: T, max: T, ss: T) -> Vec<(T,T)> {
+        let mut out = vec![];
+        let mut f = min;
+        while f <= max {
+            out.push((f, self.density(f)));
+            f += ss;
+        }
+        out
+    }
+}
+
+// burg's method, which fits the reflection coefficients order by
+// order minimizing forward and backward prediction error together
+// returns the model of every order 0..=max_order
+fn burg_models(data: &[f64], max_order: usize) -> Vec<(Vec<f64>, f64)> {
+    let n = data.len();
+    let mut forward = data.to_vec();
+    let mut backward = data.to_vec();
+    let mut a: Vec<f64> = vec![];
+    let mut error = data.iter().map(|x| x * x).sum::<f64>() / n.max(1) as f64;
+    let mut models = vec![(a.clone(), error)];
+
+    for m in 1..=max_order.min(n.saturating_sub(1)) {
+        // f[i] and b[i-1] for i in m..n
+        let (num, den) = (m..n).fold((0.0, 0.0), |acc, i| {
+            (acc.0 + forward[i] * backward[i - 1],
+             acc.1 + forward[i] * forward[i] + backward[i - 1] * backward[i - 1])
+        });
+        let k = if den > 0.0 { -2.0 * num / den } else { 0.0 };
+
+        // levinson update of the coefficients
+        let previous = a.clone();
+        a.push(k);
+        for j in 0..m - 1 {
+            a[j] = previous[j] + k * previous[m - 2 - j];
+        }
+        error *= 1.0 - k * k;
+
+        for i in (m..n).rev() {
+            let f = forward[i];
+            forward[i] = f + k * backward[i - 1];
+            backward[i] = backward[i - 1] + k * f;
+        }
+        models.push((a.clone(), error));
+    }
+    models
+}
+
+// model orders need more points than coefficients
+fn check_order(len: usize, order: usize) -> Result<(), String> {
+    if order >= len {
+        return Err(format!("order {} needs more than {} points", order, len));
+    }
+    Ok(())
+}
+
+// autoregressive model of order fitted to sample with burg's method
+// errors when the sample has no more points than order
+pub fn burg<T: Real>(sample: &Sample<T>, order: usize) -> Result<ArModel<T>, String> {
+    check_order(sample.data.len(), order)?;
+    let data: Vec<f64> = sample.data.iter().map(|x| x.as_f64()).collect();
+    let models = burg_models(&data, order);
+    let (a, error) = models.last().unwrap();
+    Ok(ArModel {
+        coefficients: a.iter().map(|&x| T::from_f64(x)).collect(),
+        variance: T::from_f64(*error),
+        rate: sample.rate,
+    })
+}
+
+// information criteria for picking a model order, both trade the
+// prediction error against the number of parameters
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrderCriterion {
+    // akaike, N ln σ² + 2m, tends to overfit long records
+    Aic,
+    // minimum description length, N ln σ² + m ln N
+    Mdl,
+}
+
+impl OrderCriterion {
+    fn cost(&self, n: usize, order: usize, error: f64) -> f64 {
+        let n = n as f64;
+        let penalty = match *self {
+            OrderCriterion::Aic => 2.0 * order as f64,
+            OrderCriterion::Mdl => order as f64 * n.ln(),
+        };
+        n * error.max(f64::MIN_POSITIVE).ln() + penalty
+    }
+}
+
+// burg model of the order up to max_order that minimizes criterion
+// errors when the sample has no more points than max_order
+pub fn burg_select<T: Real>(sample: &Sample<T>, max_order: usize, criterion: OrderCriterion) -> Result<ArModel<T>, String> {
+    check_order(sample.data.len(), max_order)?;
+    let data: Vec<f64> = sample.data.iter().map(|x| x.as_f64()).collect();
+    let models = burg_models(&data, max_order);
+    let (a, error) = models.iter()
+        .enumerate()
+        .fold(None, |best: Option<(f64, usize)>, (m, &(_, e))| {
+            let cost = criterion.cost(data.len(), m, e);
+            match best {
+                Some((c, _)) if c <= cost => best,
+                _ => Some((cost, m)),
+            }
+        })
+        .map(|(_, m)| models[m].clone())
+        .unwrap();
+    Ok(ArModel {
+        coefficients: a.iter().map(|&x| T::from_f64(x)).collect(),
+        variance: T::from_f64(error),
+        rate: sample.rate,
+    })
+}
+
+// correlation orders must leave a noise subspace beside the
+// 2 * count dimensional signal subspace and fit the sample
+fn check_subspace(len: usize, count: usize, order: usize) -> Result<(), String> {
+    if order <= 2 * count || order < 2 {
+        return Err(format!("correlation order {} leaves no noise subspace for {} lines", order, count));
+    }
+    check_order(len, order)
+}
+
+// forward-backward averaged order x order autocorrelation matrix
+fn correlation_matrix(data: &[f64], order: usize) -> Vec<Vec<f64>> {
+    assert!(order >= 2 && order < data.len(), "correlation order {} for {} points", order, data.len());
+    let snapshots = data.len() - order + 1;
+    let mut r = vec![vec![0.0; order]; order];
+    for s in 0..snapshots {
+        let x = &data[s..s + order];
+        for i in 0..order {
+            for j in i..order {
+                // backward snapshot is x reversed
+                let v = x[i] * x[j] + x[order - 1 - i] * x[order - 1 - j];
+                r[i][j] += v;
+            }
+        }
+    }
+    // scale the upper triangle and mirror it
+    let scale = 1.0 / (2 * snapshots) as f64;
+    (0..order)
+        .map(|i| (0..order).map(|j| r[i.min(j)][i.max(j)] * scale).collect())
+        .collect()
+}
+
+// eigenvalues and unit eigenvectors (as columns) of a symmetric
+// matrix, eigenvalues descending, by cyclic jacobi rotations
+fn symmetric_eigen(mut a: Vec<Vec<f64>>) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = a.len();
+    let mut v: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+    for _ in 0..100 {
+        let off: f64 = (0..n).flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j] * a[i][j])
+            .sum();
+        let total: f64 = (0..n).map(|i| a[i][i] * a[i][i]).sum::<f64>() + off;
+        if off <= 1e-30 * total {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q].abs() < 1e-300 {
+                    continue;
+                }
+                // rotation zeroing a[p][q]
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let t = if theta == 0.0 { 1.0 } else { t };
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                let (row_p, row_q) = (a[p].clone(), a[q].clone());
+                for (k, (apk, aqk)) in row_p.into_iter().zip(row_q).enumerate() {
+                    a[p][k] = c * apk - s * aqk;
+                    a[q][k] = s * apk + c * aqk;
+                }
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| a[j][j].total_cmp(&a[i][i]));
+    let values = order.iter().map(|&i| a[i][i]).collect();
+    let vectors = (0..n).map(|r| order.iter().map(|&i| v[r][i]).collect()).collect();
+    (values, vectors)
+}
+
+// solves a x = b by gaussian elimination with partial pivoting
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Vec<f64> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .fold(col, |best, r| if a[r][col].abs() > a[best][col].abs() { r } else { best });
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        if a[col][col] == 0.0 {
+            continue;
+        }
+        let pivot_row = a[col].clone();
+        for r in col + 1..n {
+            let factor = a[r][col] / pivot_row[col];
+            for (x, p) in a[r].iter_mut().zip(pivot_row.iter()).skip(col) {
+                *x -= factor * p;
+            }
+            b[r] -= factor * b[col];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for r in (0..n).rev() {
+        let sum: f64 = (r + 1..n).map(|c| a[r][c] * x[c]).sum();
+        x[r] = if a[r][r] == 0.0 { 0.0 } else { (b[r] - sum) / a[r][r] };
+    }
+    x
+}
+
+// amplitudes and phases of sinusoids at the given frequencies by
+// least squares, in the graph_circle convention where a sine of
+// amplitude a and phase p gives a/2 e^(ip)
+fn fit_lines<T: Real>(data: &[f64], rate: usize, frequencies: &[f64]) -> Vec<Phasor<T>> {
+    // columns cos(wt), sin(wt) per frequency
+    let basis = |n: usize, j: usize| {
+        let w = 2.0 * PI * frequencies[j / 2] * n as f64 / rate as f64;
+        if j.is_multiple_of(2) { w.cos() } else { w.sin() }
+    };
+    let m = 2 * frequencies.len();
+    let normal: Vec<Vec<f64>> = (0..m)
+        .map(|i| (0..m).map(|j| (0..data.len()).map(|n| basis(n, i) * basis(n, j)).sum()).collect())
+        .collect();
+    let rhs: Vec<f64> = (0..m).map(|i| data.iter().enumerate().map(|(n, y)| basis(n, i) * y).sum()).collect();
+    let x = solve(normal, rhs);
+
+    frequencies.iter()
+        .enumerate()
+        .map(|(j, &f)| {
+            // a cos + b sin = hypot(a, b) sin(wt + atan2(a, b))
+            let (a, b) = (x[2 * j], x[2 * j + 1]);
+            let c = Complex::from_polar(&(0.5 * a.hypot(b)), &a.atan2(b));
+            Phasor{ frequency: T::from_f64(f), complex: Complex{ re: T::from_f64(c.re), im: T::from_f64(c.im) } }
+        })
+        .collect()
+}
+
+// music pseudo-spectrum and its strongest lines
+pub struct Pseudospectrum<T = f32> {
+    pub frequencies: Vec<T>,
+    // 1 / distance of the steering vector to the signal subspace,
+    // peaks where it lies inside, not a power
+    pub power: Vec<T>,
+    // highest local maxima, one per requested line, ascending
+    pub lines: Vec<Phasor<T>>,
+}
+
+// music over a min..max sweep for sample holding count real
+// sinusoids in white noise
+// order is the size of the correlation matrix, more than 2 * count
+// and well below the sample length, errors otherwise
+pub fn music<T: Real>(sample: &Sample<T>, count: usize, order: usize, min: T, max: T, ss: T) -> Result<Pseudospectrum<T>, String> {
+    check_subspace(sample.data.len(), count, order)?;
+    let data: Vec<f64> = sample.data.iter().map(|x| x.as_f64()).collect();
+    let (_, vectors) = symmetric_eigen(correlation_matrix(&data, order));
+
+    let mut frequencies = vec![];
+    let mut power = vec![];
+    let mut f = min;
+    while f <= max {
+        let w = 2.0 * PI * f.as_f64() / sample.rate as f64;
+        // projection of e(w) onto the noise eigenvectors
+        let noise: f64 = (2 * count..order)
+            .map(|j| {
+                (0..order)
+                    .fold(Complex{ re: 0.0, im: 0.0 }, |acc, i| acc + Complex::from_polar(&vectors[i][j], &(w * i as f64)))
+                    .norm_sqr()
+            })
+            .sum();
+        frequencies.push(f.as_f64());
+        power.push(1.0 / noise.max(1e-300));
+        f += ss;
+    }
+
+    // nan never compares as a maximum, infinite power is dropped too
+    let mut peaks: Vec<usize> = (1..power.len().saturating_sub(1))
+        .filter(|&i| power[i].is_finite() && power[i] > power[i - 1] && power[i] >= power[i + 1])
+        .collect();
+    peaks.sort_by(|&a, &b| power[b].total_cmp(&power[a]));
+    let mut found: Vec<f64> = peaks.iter().take(count).map(|&i| frequencies[i]).collect();
+    found.sort_by(|a, b| a.total_cmp(b));
+
+    Ok(Pseudospectrum {
+        lines: fit_lines(&data, sample.rate, &found),
+        frequencies: frequencies.iter().map(|&f| T::from_f64(f)).collect(),
+        power: power.iter().map(|&p| T::from_f64(p)).collect(),
+    })
+}
+
+// coefficients c[0..n] of det(λI - a) = λ^n + c[n-1] λ^(n-1) + ... + c[0]
+// (faddeev-leverrier)
+fn characteristic_polynomial(a: &[Vec<f64>]) -> Vec<f64> {
+    let n = a.len();
+    let mut c = vec![0.0; n + 1];
+    c[n] = 1.0;
+    let mut m = vec![vec![0.0; n]; n];
+    for k in 1..=n {
+        // m = a m + c[n-k+1] I
+        let am: Vec<Vec<f64>> = (0..n)
+            .map(|i| (0..n).map(|j| (0..n).map(|l| a[i][l] * m[l][j]).sum()).collect())
+            .collect();
+        m = am;
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] += c[n - k + 1];
+        }
+        let trace: f64 = (0..n).map(|i| (0..n).map(|l| a[i][l] * m[l][i]).sum::<f64>()).sum();
+        c[n - k] = -trace / k as f64;
+    }
+    c
+}
+
+// complex roots of a monic polynomial (durand-kerner)
+fn polynomial_roots(c: &[f64]) -> Vec<Complex<f64>> {
+    let n = c.len() - 1;
+    let eval = |z: Complex<f64>| c.iter().rev().fold(Complex{ re: 0.0, im: 0.0 }, |acc, &k| acc * z + k);
+    let seed = Complex{ re: 0.4f64, im: 0.9 };
+    let mut roots: Vec<Complex<f64>> = (0..n).map(|k| Complex::from_polar(&seed.norm().powi(k as i32), &(seed.arg() * k as f64))).collect();
+    for _ in 0..1000 {
+        let mut change: f64 = 0.0;
+        for i in 0..n {
+            let denominator = (0..n)
+                .filter(|&j| j != i)
+                .fold(Complex{ re: 1.0, im: 0.0 }, |acc, j| acc * (roots[i] - roots[j]));
+            let step = eval(roots[i]) / denominator;
+            roots[i] -= step;
+            change = change.max(step.norm());
+        }
+        if change < 1e-14 {
+            break;
+        }
+    }
+    roots
+}
+
+// esprit line estimates for sample holding count real sinusoids
+// the signal subspace of the correlation matrix shifted by one
+// sample equals itself rotated by e^(±iw) of every line, so the
+// eigenvalues of the least squares rotation give the frequencies
+// order is bounded as for music
+pub fn esprit<T: Real>(sample: &Sample<T>, count: usize, order: usize) -> Result<Vec<Phasor<T>>, String> {
+    check_subspace(sample.data.len(), count, order)?;
+    let data: Vec<f64> = sample.data.iter().map(|x| x.as_f64()).collect();
+    let dim = 2 * count;
+    let (_, vectors) = symmetric_eigen(correlation_matrix(&data, order));
+
+    // signal subspace without its last and first row
+    let upper = |i: usize, j: usize| vectors[i][j];
+    let lower = |i: usize, j: usize| vectors[i + 1][j];
+    // phi = (U1' U1)^-1 U1' U2, solved column by column
+    let gram: Vec<Vec<f64>> = (0..dim)
+        .map(|i| (0..dim).map(|j| (0..order - 1).map(|r| upper(r, i) * upper(r, j)).sum()).collect())
+        .collect();
+    let columns: Vec<Vec<f64>> = (0..dim)
+        .map(|j| {
+            let rhs = (0..dim).map(|i| (0..order - 1).map(|r| upper(r, i) * lower(r, j)).sum()).collect();
+            solve(gram.clone(), rhs)
+        })
+        .collect();
+    let phi: Vec<Vec<f64>> = (0..dim).map(|i| (0..dim).map(|j| columns[j][i]).collect()).collect();
+
+    // lines of a real signal are conjugate pairs e^(±iw), or single
+    // real roots at dc and nyquist, paired up explicitly since
+    // rounding leaves either sign on near real imaginary parts
+    let roots = polynomial_roots(&characteristic_polynomial(&phi));
+    let mut paired = vec![false; roots.len()];
+    // (distance from the unit circle, frequency) per line
+    let mut candidates = vec![];
+    for i in 0..roots.len() {
+        if paired[i] {
+            continue;
+        }
+        paired[i] = true;
+        let conj = roots[i].conj();
+        let partner = (0..roots.len())
+            .filter(|&j| !paired[j])
+            .min_by(|&a, &b| (roots[a] - conj).norm().total_cmp(&(roots[b] - conj).norm()));
+        if let Some(j) = partner {
+            if (roots[j] - conj).norm() < 1e-6 {
+                paired[j] = true;
+            }
+        }
+        let frequency = roots[i].arg().abs() * sample.rate as f64 / (2.0 * PI);
+        candidates.push(((roots[i].norm() - 1.0).abs(), frequency));
+    }
+    // undamped lines lie on the unit circle, the rest is noise
+    candidates.retain(|c: &(f64, f64)| c.0.is_finite() && c.1.is_finite());
+    candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut frequencies: Vec<f64> = candidates.iter().take(count).map(|c| c.1).collect();
+    frequencies.sort_by(|a, b| a.total_cmp(b));
+    Ok(fit_lines(&data, sample.rate, &frequencies))
+}
+
+#[cfg(test)]
+mod tests {
+use super::*;
+use tests::noise;
+use util;
+
+// 100 ms of tones 5 hz apart, half the fourier resolution
+fn close_tones() -> Sample<f64> {
+    let mut sample: Sample<f64> = util::sinewaves(0.1, 1000, &[(100.0,30.0),(105.0,0.0)]);
+    for (x, n) in sample.data.iter_mut().zip(noise(100, 21)) {
+        *x += 0.001 * n as f64;
+    }
+    sample
+}
+
+#[test]
+    fn test_burg_ar2(){
+        // x[n] = 1.5 x[n-1] - 0.8 x[n-2] + e[n]
+        let e = noise(4000, 17);
+        let mut data = vec![0.0f64; e.len()];
+        for n in 2..e.len() {
+            data[n] = 1.5 * data[n - 1] - 0.8 * data[n - 2] + e[n] as f64;
+        }
+        let sample = Sample{ data, rate: 1000 };
+
+        let model = burg(&sample, 2).unwrap();
+        assert!((model.coefficients[0] + 1.5).abs() < 0.02);
+        assert!((model.coefficients[1] - 0.8).abs() < 0.02);
+        // driving noise is uniform in -1..1, variance 1/3
+        assert!((model.variance - 1.0 / 3.0).abs() < 0.02);
+
+        for &criterion in &[OrderCriterion::Aic, OrderCriterion::Mdl] {
+            assert_eq!(burg_select(&sample, 12, criterion).unwrap().order(), 2, "{:?}", criterion);
+        }
+
+        // resonance of the poles at acos(1.5 / (2 sqrt 0.8)) rad per sample
+        let peak = (2.0f64 * 0.8f64.sqrt()).recip() * 1.5;
+        let f0 = peak.acos() * 1000.0 / (2.0 * PI);
+        let sweep = model.sweep(0.0, 500.0, 0.5);
+        let best = sweep.iter().fold((0.0, 0.0), |acc, &(f, d)| if d > acc.1 { (f, d) } else { acc });
+        assert!((best.0 - f0).abs() < 3.0);
+    }
+
+#[test]
+    fn test_burg_resolves_close_tones(){
+        let model = burg(&close_tones(), 10).unwrap();
+        let sweep = model.sweep(80.0, 125.0, 0.25);
+        let peaks: Vec<f64> = (1..sweep.len() - 1)
+            .filter(|&i| sweep[i].1 > sweep[i - 1].1 && sweep[i].1 >= sweep[i + 1].1)
+            .map(|i| sweep[i].0)
+            .collect();
+        assert_eq!(peaks.len(), 2, "{:?}", peaks);
+        // burg frequencies are biased by the tone phases, but the two
+        // peaks stay apart where a 100 ms periodogram shows one
+        assert!((peaks[0] - 100.0).abs() < 2.0 && (peaks[1] - 105.0).abs() < 2.0, "{:?}", peaks);
+    }
+
+#[test]
+    fn test_music_esprit_close_tones(){
+        let sample = close_tones();
+        let music = music(&sample, 2, 30, 80.0, 125.0, 0.05).unwrap();
+        assert_eq!(music.power.len(), music.frequencies.len());
+        for lines in &[music.lines, esprit(&sample, 2, 30).unwrap()] {
+            assert_eq!(lines.len(), 2);
+            assert!((lines[0].frequency - 100.0).abs() < 0.2, "{}", lines[0].frequency);
+            assert!((lines[1].frequency - 105.0).abs() < 0.2, "{}", lines[1].frequency);
+            // unit sines give phasors of 0.5 at their phase
+            let (r0, p0) = lines[0].complex.to_polar();
+            let (r1, p1) = lines[1].complex.to_polar();
+            assert!((r0 - 0.5).abs() < 0.02 && (r1 - 0.5).abs() < 0.02);
+            assert!((p0.to_degrees() - 30.0).abs() < 3.0 && p1.to_degrees().abs() < 3.0);
+        }
+    }
+
+#[test]
+    fn test_esprit_edge_lines(){
+        // lines at or near dc and nyquist give roots on or close to the
+        // real axis, next to spurious real roots inside the unit circle
+        for &(f, g) in &[(0.0, 120.0), (3.0, 120.0), (0.5, 200.0), (120.0, 497.0), (120.0, 500.0)] {
+            let mut sample: Sample<f64> = util::sinewaves(0.2, 1000, &[(f,60.0),(g,90.0)]);
+            for (x, n) in sample.data.iter_mut().zip(noise(200, 23)) {
+                *x += 0.001 * n as f64;
+            }
+            let lines = esprit(&sample, 2, 30).unwrap();
+            assert_eq!(lines.len(), 2);
+            assert!((lines[0].frequency - f).abs() < 0.2, "{} for {}", lines[0].frequency, f);
+            assert!((lines[1].frequency - g).abs() < 0.2, "{} for {}", lines[1].frequency, g);
+        }
+    }
+
+#[test]
+    fn test_music_nan(){
+        // degenerate input yields no lines instead of panicking
+        let mut sample = close_tones();
+        sample.data[10] = f64::NAN;
+        let music = music(&sample, 2, 30, 80.0, 125.0, 0.5).unwrap();
+        assert!(music.lines.is_empty());
+    }
+
+#[test]
+    fn test_order_errors(){
+        // well formed but too short for the order
+        let short = Sample{ data: noise(20, 3), rate: 1000 };
+        assert!(burg(&short, 20).is_err());
+        assert!(burg(&short, 19).is_ok());
+        assert!(burg_select(&short, 25, OrderCriterion::Aic).is_err());
+        assert!(music(&short, 2, 20, 80.0, 125.0, 0.5).is_err());
+        assert!(esprit(&short, 2, 30).is_err());
+        assert!(esprit(&short, 2, 10).is_ok());
+
+        // no noise subspace left
+        assert!(music(&short, 2, 4, 80.0, 125.0, 0.5).is_err());
+        assert!(esprit(&short, 0, 1).is_err());
+        let empty: Sample = Sample{ data: vec![], rate: 1000 };
+        assert!(burg(&empty, 0).is_err());
+    }
+}
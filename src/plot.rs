@@ -30,7 +30,7 @@ fn complex_to_plot(data: &[Complex<f32>]) -> Vec<(f64,f64)> {
     data.iter().map(|&c| (c.re as f64, c.im as f64)).collect()
 }
 
-pub fn draw_circle(mut term: &mut DTerm, data: &[Complex<f32>]) {
+pub fn draw_circle(term: &mut DTerm, data: &[Complex<f32>]) {
     let data = complex_to_plot(data);
     // plot scale from min/max values
     let r = data.iter().fold(0.0, |acc: f64,xy|{
@@ -53,10 +53,10 @@ pub fn draw_circle(mut term: &mut DTerm, data: &[Complex<f32>]) {
                   .marker(Marker::Braille)
                   .style(Style::default().fg(Color::White))
                   .data(&data)])
-        .render(&mut term, &Rect::new(0,0,w,h));
+        .render(term, &Rect::new(0,0,w,h));
 }
 
-pub fn draw_waveform(mut term: &mut DTerm, sample: &fft::Sample, color: Color) {
+pub fn draw_waveform(term: &mut DTerm, sample: &fft::Sample, color: Color) {
     // plot scale from min/max values
     let r = sample.max_amplitude().ceil() as f64;
 
@@ -67,18 +67,18 @@ pub fn draw_waveform(mut term: &mut DTerm, sample: &fft::Sample, color: Color) {
     let w = size.width - x;
 
     let min = 0.0;
-    let max = (sample.data.len() as f64 / sample.rate as f64) as f64;
+    let max = sample.data.len() as f64 / sample.rate as f64;
 
     Chart::<&str,&str>::default()
         .block(Block::default()
                .title("Waveform")
                .borders(Borders::ALL))
         .x_axis(Axis::default()
-                .bounds([min,max.ceil() as f64])
+                .bounds([min,max.ceil()])
                 .labels(&[&min.to_string(), 
                         &format!("{:.2}", min + (max - min) / 4.0),
                         &format!("{:.2}", min + (max - min) / 2.0),
-                        &format!("{:.2}", min + (max - min) / 1.3333333333333333333),
+                        &format!("{:.2}", min + (max - min) / 1.333_333_3),
                         &format!("{:.2}", max)])
                 )
         .y_axis(Axis::default()
@@ -87,10 +87,10 @@ pub fn draw_waveform(mut term: &mut DTerm, sample: &fft::Sample, color: Color) {
                   .marker(Marker::Braille)
                   .style(Style::default().fg(color))
                   .data(&sample.with_time(100000))])
-        .render(&mut term, &Rect::new(x,y,w,h));
+        .render(term, &Rect::new(x,y,w,h));
 }
 
-pub fn draw_frequency_graph(mut term: &mut DTerm, data: &[fft::Phasor], min: f32, max: f32) {
+pub fn draw_frequency_graph(term: &mut DTerm, data: &[fft::Phasor], min: f32, max: f32) {
     // plot scale from min/max values
     let r = 1.0;
     // let r = data.iter().fold(0.0, |acc: f32,xy|{
@@ -111,7 +111,7 @@ pub fn draw_frequency_graph(mut term: &mut DTerm, data: &[fft::Phasor], min: f32
                 .labels(&[&min.to_string(), 
                         &(min + (max - min) / 4.0).to_string(),
                         &(min + (max - min) / 2.0).to_string(),
-                        &(min + (max - min) / 1.3333333333333333333).to_string(),
+                        &(min + (max - min) / 1.333_333_3).to_string(),
                         &max.to_string()])
                 )
         .y_axis(Axis::default()
@@ -120,20 +120,15 @@ pub fn draw_frequency_graph(mut term: &mut DTerm, data: &[fft::Phasor], min: f32
                   .marker(Marker::Braille)
                   .style(Style::default().fg(Color::White))
                   .data(&phasor_to_plot(data))])
-        .render(&mut term, &Rect::new(0,y,w,h));
+        .render(term, &Rect::new(0,y,w,h));
 }
 
 
+#[derive(Default)]
 struct Label<'a> {
     text: &'a str,
 }
 
-impl<'a> Default for Label<'a> {
-    fn default() -> Label<'a> {
-        Label { text: "" }
-    }
-}
-
 impl<'a> Widget for Label<'a> {
     fn draw(&mut self, area: &Rect, buf: &mut Buffer) {
         buf.set_string(area.left(), area.top(), self.text, &Style::default());
@@ -147,7 +142,7 @@ impl<'a> Label<'a> {
     }
 }
 
-pub fn draw_peaks(mut term: &mut DTerm, data: &Vec<&fft::Phasor>, min: f32, max: f32) {
+pub fn draw_peaks(term: &mut DTerm, data: &Vec<&fft::Phasor>, min: f32, max: f32) {
     // plot scale from min/max values
     let r = 1.0;
     let size = &term.size().unwrap();
@@ -164,7 +159,7 @@ pub fn draw_peaks(mut term: &mut DTerm, data: &Vec<&fft::Phasor>, min: f32, max:
                   .marker(Marker::Dot)
                   .style(Style::default().fg(Color::Red))
                   .data(&phasor_ref_to_plot(&data[..]))])
-        .render(&mut term, &Rect::new(0,y,w,h));
+        .render(term, &Rect::new(0,y,w,h));
 
     // draw labels
     let l = max - min;
@@ -174,7 +169,7 @@ pub fn draw_peaks(mut term: &mut DTerm, data: &Vec<&fft::Phasor>, min: f32, max:
         let degrees = theta * 180.0 / PI;
         Label::default()
             .text(&format!("{:.2}:{:.2}", p.frequency, degrees))
-            .render(&mut term, 
+            .render(term, 
                     &Rect::new(x,y + (h / 2) - (r * (h / 2) as f32) as u16,0,0));
     });
 }
@@ -0,0 +1,277 @@
+// power spectral density estimation
+// welch's method averages the periodograms of overlapping windowed
+// segments, trading frequency resolution for a lower variance
+
+use std::fmt;
+use std::str::FromStr;
+use num_complex::Complex;
+use num_traits::Zero;
+use super::{Sample, Real};
+use real::RealFftPlan;
+use window::Window;
+
+// trend removed from every segment before windowing
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum Detrend {
+    None,
+    // subtract the segment mean
+    #[default]
+    Mean,
+    // subtract the least squares line
+    Linear,
+}
+
+impl Detrend {
+    pub fn apply<T: Real>(&self, data: &mut [T]) {
+        let n = data.len();
+        if n == 0 {
+            return;
+        }
+        match *self {
+            Detrend::None => {}
+            Detrend::Mean => {
+                let mean = data.iter().map(|x| x.as_f64()).sum::<f64>() / n as f64;
+                for x in data.iter_mut() {
+                    *x -= T::from_f64(mean);
+                }
+            }
+            Detrend::Linear => {
+                // fit y = a + b (i - center)
+                let center = (n - 1) as f64 / 2.0;
+                let (sy, sxy, sxx) = data.iter()
+                    .enumerate()
+                    .fold((0.0, 0.0, 0.0), |acc, (i, x)| {
+                        let d = i as f64 - center;
+                        (acc.0 + x.as_f64(), acc.1 + d * x.as_f64(), acc.2 + d * d)
+                    });
+                let a = sy / n as f64;
+                let b = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+                for (i, x) in data.iter_mut().enumerate() {
+                    *x -= T::from_f64(a + b * (i as f64 - center));
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Detrend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Detrend, String> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Detrend::None),
+            "mean" | "constant" => Ok(Detrend::Mean),
+            "linear" => Ok(Detrend::Linear),
+            name => Err(format!("unknown detrend '{}'", name)),
+        }
+    }
+}
+
+impl fmt::Display for Detrend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Detrend::None => write!(f, "none"),
+            Detrend::Mean => write!(f, "mean"),
+            Detrend::Linear => write!(f, "linear"),
+        }
+    }
+}
+
+// Options for welch estimation
+#[derive(Clone)]
+pub struct WelchOptions {
+    // points per segment, shortened to the sample length if longer
+    pub segment: usize,
+    // points shared by neighbouring segments, less than segment
+    pub overlap: usize,
+    pub window: Window,
+    pub detrend: Detrend,
+}
+
+impl WelchOptions {
+    // segments must be non-empty and overlap by less than their length
+    pub fn validate(&self) -> Result<(), String> {
+        if self.segment == 0 {
+            return Err("welch segment length must be positive".to_string());
+        }
+        if self.overlap >= self.segment {
+            return Err(format!("welch overlap {} must be less than the segment length {}",
+                               self.overlap, self.segment));
+        }
+        Ok(())
+    }
+}
+
+impl Default for WelchOptions {
+    // hann segments of 256 points overlapping by half
+    fn default() -> WelchOptions {
+        WelchOptions { segment: 256, overlap: 128, window: Window::Hann, detrend: Detrend::Mean }
+    }
+}
+
+// one-sided power spectrum estimate
+pub struct Psd<T = f32> {
+    // bin frequencies 0..=rate/2, hz
+    pub frequencies: Vec<T>,
+    // power spectral density, V²/Hz
+    // integrates over frequency to the signal's mean power
+    pub density: Vec<T>,
+    // power spectrum, V²
+    // a tone of amplitude a on a bin reads a²/2 there
+    pub spectrum: Vec<T>,
+    // number of averaged segments
+    pub segments: usize,
+    // points per segment and overlap actually used, less than the
+    // options when the sample is shorter than a segment
+    pub len: usize,
+    pub overlap: usize,
+}
+
+impl<T: Real> Psd<T> {
+    // frequency spacing between two bins, hz
+    pub fn spacing(&self) -> T {
+        if self.frequencies.len() < 2 {
+            return T::zero();
+        }
+        self.frequencies[1] - self.frequencies[0]
+    }
+}
+
+// one-sided welch estimate of sample's power spectrum
+// errors on options that fail validate() and on an empty sample
+// a sample shorter than options.segment is taken as one segment of
+// its own length, with the overlap clamped below it, and the psd
+// reports the len and overlap actually used
+pub fn welch<T: Real>(sample: &Sample<T>, options: &WelchOptions) -> Result<Psd<T>, String> {
+    options.validate()?;
+    let len = sample.data.len();
+    if len == 0 {
+        return Err("welch needs a non-empty sample".to_string());
+    }
+    let segment = options.segment.min(len);
+    let overlap = options.overlap.min(segment - 1);
+    let hop = segment - overlap;
+    let count = (len - segment) / hop + 1;
+
+    let plan = RealFftPlan::new(segment);
+    let coefficients: Vec<T> = options.window.coefficients(segment);
+    let bins = segment / 2 + 1;
+
+    // averaged |X|²
+    let mut sum = vec![0.0; bins];
+    let mut data = vec![T::zero(); segment];
+    let mut spectrum = vec![Complex::zero(); bins];
+    let mut scratch = vec![Complex::zero(); plan.scratch_len()];
+    for i in 0..count {
+        data.copy_from_slice(&sample.data[i * hop..i * hop + segment]);
+        options.detrend.apply(&mut data);
+        for (x, &w) in data.iter_mut().zip(coefficients.iter()) {
+            *x *= w;
+        }
+        plan.forward_with_scratch(&data, &mut spectrum, &mut scratch);
+        for (s, c) in sum.iter_mut().zip(spectrum.iter()) {
+            *s += c.norm_sqr().as_f64();
+        }
+    }
+
+    // |X|² / (fs Σw²) is a density, |X|² / (Σw)² a power,
+    // both doubled off dc and nyquist to fold in negative frequencies
+    let rate = sample.rate as f64;
+    let (w_sum, w_sq) = coefficients.iter()
+        .fold((0.0, 0.0), |acc, w| (acc.0 + w.as_f64(), acc.1 + w.as_f64() * w.as_f64()));
+    let one_sided = |k: usize| if k == 0 || (segment.is_multiple_of(2) && k == segment / 2) { 1.0 } else { 2.0 };
+    let mean = |k: usize| sum[k] / count as f64 * one_sided(k);
+
+    Ok(Psd {
+        frequencies: (0..bins).map(|k| T::from_f64(k as f64 * rate / segment as f64)).collect(),
+        density: (0..bins).map(|k| T::from_f64(mean(k) / (rate * w_sq))).collect(),
+        spectrum: (0..bins).map(|k| T::from_f64(mean(k) / (w_sum * w_sum))).collect(),
+        segments: count,
+        len: segment,
+        overlap,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+use super::*;
+use tests::noise;
+use util;
+
+#[test]
+    fn test_welch_tone(){
+        // 125 hz sits on a bin of 256 point segments at 1000 hz
+        let sample: Sample = util::sinewave(125.0, 0.0, 4.0, 1000, 2.0);
+        let psd = welch(&sample, &WelchOptions::default()).unwrap();
+        assert_eq!(psd.frequencies.len(), 129);
+        assert_eq!(psd.segments, (4000 - 256) / 128 + 1);
+        assert_eq!(psd.frequencies[32], 125.0);
+
+        // power spectrum reads a²/2 at the tone
+        assert!((psd.spectrum[32] - 2.0).abs() < 1e-3);
+        // density integrates to the mean power a²/2
+        let total: f32 = psd.density.iter().sum::<f32>() * psd.spacing();
+        assert!((total - 2.0).abs() < 1e-2);
+    }
+
+#[test]
+    fn test_welch_noise_floor(){
+        // uniform noise in -1..1 has variance 1/3, spread evenly
+        // over 0..rate/2 the one-sided density is 2/3 / rate
+        let sample = Sample{ data: noise(100_000, 3), rate: 1000 };
+        let psd = welch(&sample, &WelchOptions{ window: Window::Hamming, ..WelchOptions::default() }).unwrap();
+        let interior = &psd.density[1..psd.density.len() - 1];
+        let mean = interior.iter().sum::<f32>() / interior.len() as f32;
+        assert!((mean - 2.0 / 3.0 / 1000.0).abs() < 2e-5);
+        // averaging keeps every bin near the floor
+        assert!(interior.iter().all(|&d| (d - mean).abs() < 0.3 * mean));
+    }
+
+#[test]
+    fn test_welch_detrend(){
+        // a ramp leaks into the low bins unless removed
+        let mut sample: Sample = util::sinewave(125.0, 0.0, 2.0, 1000, 1.0);
+        for (i, x) in sample.data.iter_mut().enumerate() {
+            *x += i as f32 * 0.01;
+        }
+        let low = |detrend: Detrend| {
+            let psd = welch(&sample, &WelchOptions{ detrend, ..WelchOptions::default() }).unwrap();
+            psd.spectrum[..4].iter().sum::<f32>()
+        };
+        assert!(low(Detrend::None) > 100.0);
+        assert!(low(Detrend::Mean) > 0.1);
+        assert!(low(Detrend::Linear) < 1e-3);
+
+        assert_eq!("linear".parse::<Detrend>(), Ok(Detrend::Linear));
+        assert!("cubic".parse::<Detrend>().is_err());
+    }
+
+#[test]
+    fn test_welch_short_sample(){
+        // segments longer than the sample shrink to it
+        let sample: Sample = util::sinewave(50.0, 0.0, 0.1, 1000, 1.0);
+        let psd = welch(&sample, &WelchOptions{ segment: 1024, overlap: 512, ..WelchOptions::default() }).unwrap();
+        assert_eq!(psd.segments, 1);
+        assert_eq!(psd.frequencies.len(), 51);
+        assert_eq!((psd.len, psd.overlap), (100, 99));
+    }
+
+#[test]
+    fn test_welch_overlap_validation(){
+        let options = WelchOptions{ segment: 200, overlap: 300, ..WelchOptions::default() };
+        assert!(options.validate().is_err());
+        assert!(WelchOptions{ overlap: 200, ..options.clone() }.validate().is_err());
+        assert!(WelchOptions{ segment: 0, overlap: 0, ..options.clone() }.validate().is_err());
+        assert!(WelchOptions{ overlap: 199, ..options.clone() }.validate().is_ok());
+    }
+
+#[test]
+    fn test_welch_rejects_overlap(){
+        let sample: Sample = util::sinewave(50.0, 0.0, 1.0, 1000, 1.0);
+        let result = welch(&sample, &WelchOptions{ segment: 200, overlap: 300, ..WelchOptions::default() });
+        assert_eq!(result.err().unwrap(), "welch overlap 300 must be less than the segment length 200");
+
+        let empty: Sample = Sample{ data: vec![], rate: 1000 };
+        assert!(welch(&empty, &WelchOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,383 @@
+// real input transforms
+// sample data is always real, so only N/2+1 bins carry information,
+// the rest mirror them as X[N-k] = conj(X[k])
+
+use num_complex::Complex;
+use num_traits::Zero;
+use super::{Sample, Phasor, Real};
+use transform::{self, FftPlan};
+use window::Window;
+
+// non-redundant half of a real signal's spectrum
+pub struct HalfSpectrum<T = f32> {
+    // bins 0..=N/2
+    pub bins: Vec<Complex<T>>,
+    // length of the time domain signal
+    pub len: usize,
+    // samplerate of the time domain signal
+    pub rate: usize,
+}
+
+impl<T: Real> HalfSpectrum<T> {
+    // frequency spacing between two bins, hz
+    pub fn spacing(&self) -> T {
+        T::from_f64(self.rate as f64 / self.len as f64)
+    }
+
+    // frequency of bin k, hz
+    pub fn frequency(&self, k: usize) -> T {
+        T::from_f64(k as f64 * self.rate as f64 / self.len as f64)
+    }
+
+    pub fn frequencies(&self) -> Vec<T> {
+        (0..self.bins.len()).map(|k| self.frequency(k)).collect()
+    }
+
+    // returns bin k of the full spectrum, k in 0..len
+    pub fn bin(&self, k: usize) -> Complex<T> {
+        if k < self.bins.len() {
+            self.bins[k]
+        } else {
+            self.bins[self.len - k].conj()
+        }
+    }
+
+    // bins as phasors in the graph_circle convention
+    pub fn phasors(&self) -> Vec<Phasor<T>> {
+        self.bins.iter()
+            .enumerate()
+            .map(|(k, &c)| Phasor {
+                frequency: self.frequency(k),
+                complex: transform::bin_to_phasor(c, self.len),
+            })
+            .collect()
+    }
+}
+
+// precomputed real transform for a fixed length
+// even lengths pack the signal into a complex transform of half
+// the length, odd lengths fall back to a full complex transform
+pub struct RealFftPlan<T = f32> {
+    len: usize,
+    inner: FftPlan<T>,
+    // e^(-2πik/N) for k in 0..N/2, used to split even lengths
+    twiddles: Vec<Complex<T>>,
+}
+
+impl<T: Real> RealFftPlan<T> {
+    pub fn new(len: usize) -> RealFftPlan<T> {
+        let packed = len > 0 && len.is_multiple_of(2);
+        let inner = FftPlan::new(if packed { len / 2 } else { len });
+        let twiddles = if packed {
+            (0..len / 2)
+                .map(|k| transform::cis(-2.0 * std::f64::consts::PI * k as f64 / len as f64))
+                .collect()
+        } else {
+            vec![]
+        };
+        RealFftPlan { len, inner, twiddles }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn packed(&self) -> bool {
+        !self.twiddles.is_empty()
+    }
+
+    // scratch length the _with_scratch transforms need
+    pub fn scratch_len(&self) -> usize {
+        let work = if self.packed() { self.len / 2 } else { self.len };
+        work + self.inner.scratch_len()
+    }
+
+    // forward transform, returns bins 0..=N/2
+    pub fn forward(&self, input: &[T]) -> Vec<Complex<T>> {
+        let mut output = vec![Complex::zero(); self.len / 2 + 1];
+        let mut scratch = vec![Complex::zero(); self.scratch_len()];
+        self.forward_with_scratch(input, &mut output, &mut scratch);
+        output
+    }
+
+    // forward transform into output bins 0..=N/2 that never allocates,
+    // scratch must hold at least scratch_len() points
+    pub fn forward_with_scratch(&self, input: &[T], output: &mut [Complex<T>], scratch: &mut [Complex<T>]) {
+        assert_eq!(input.len(), self.len, "plan length {} does not match data length {}",
+                   self.len, input.len());
+        assert_eq!(output.len(), self.len / 2 + 1, "expected {} bins, got {}",
+                   self.len / 2 + 1, output.len());
+        assert!(scratch.len() >= self.scratch_len(), "scratch of {} points, plan needs {}",
+                scratch.len(), self.scratch_len());
+        if self.len == 0 {
+            return;
+        }
+
+        if !self.packed() {
+            let (data, rest) = scratch.split_at_mut(self.len);
+            for (c, &a) in data.iter_mut().zip(input.iter()) {
+                *c = Complex{ re: a, im: T::zero() };
+            }
+            self.inner.process_with_scratch(data, rest);
+            output.copy_from_slice(&data[..self.len / 2 + 1]);
+            return;
+        }
+
+        // z[m] = x[2m] + i x[2m+1]
+        let m = self.len / 2;
+        let (z, rest) = scratch.split_at_mut(m);
+        for (c, p) in z.iter_mut().zip(input.chunks(2)) {
+            *c = Complex{ re: p[0], im: p[1] };
+        }
+        self.inner.process_with_scratch(z, rest);
+
+        // split into transforms of even and odd samples
+        // and combine with one radix-2 step
+        let half = T::from_f64(0.5);
+        for (k, out) in output.iter_mut().enumerate() {
+            let a = z[k % m];
+            let b = z[(m - k) % m].conj();
+            let even = (a + b) * half;
+            let odd = (a - b) * Complex{ re: T::zero(), im: -half };
+            let w = if k < m { self.twiddles[k] } else { Complex{ re: -T::one(), im: T::zero() } };
+            *out = even + w * odd;
+        }
+    }
+
+    // inverse transform from bins 0..=N/2, scaled by 1/N
+    // so that inverse(forward(x)) == x
+    pub fn inverse(&self, bins: &[Complex<T>]) -> Vec<T> {
+        let mut output = vec![T::zero(); self.len];
+        let mut scratch = vec![Complex::zero(); self.scratch_len()];
+        self.inverse_with_scratch(bins, &mut output, &mut scratch);
+        output
+    }
+
+    // inverse transform into output that never allocates
+    pub fn inverse_with_scratch(&self, bins: &[Complex<T>], output: &mut [T], scratch: &mut [Complex<T>]) {
+        assert_eq!(bins.len(), self.len / 2 + 1, "expected {} bins, got {}",
+                   self.len / 2 + 1, bins.len());
+        assert_eq!(output.len(), self.len, "plan length {} does not match output length {}",
+                   self.len, output.len());
+        assert!(scratch.len() >= self.scratch_len(), "scratch of {} points, plan needs {}",
+                scratch.len(), self.scratch_len());
+        if self.len == 0 {
+            return;
+        }
+        // inverse through conjugated forward transform:
+        // ifft(x) = conj(fft(conj(x))) / N
+        if !self.packed() {
+            let (data, rest) = scratch.split_at_mut(self.len);
+            for (k, c) in data.iter_mut().enumerate() {
+                *c = if k < bins.len() { bins[k].conj() } else { bins[self.len - k] };
+            }
+            self.inner.process_with_scratch(data, rest);
+            let scale = T::from_f64(1.0 / self.len as f64);
+            for (x, c) in output.iter_mut().zip(data.iter()) {
+                *x = c.re * scale;
+            }
+            return;
+        }
+
+        // rebuild the packed half length spectrum
+        let m = self.len / 2;
+        let half = T::from_f64(0.5);
+        let (z, rest) = scratch.split_at_mut(m);
+        for (k, c) in z.iter_mut().enumerate() {
+            let a = bins[k];
+            let b = bins[m - k].conj();
+            let even = (a + b) * half;
+            let odd = (a - b) * half * self.twiddles[k].conj();
+            // conjugated for the inverse
+            *c = (even + Complex{ re: T::zero(), im: T::one() } * odd).conj();
+        }
+        self.inner.process_with_scratch(z, rest);
+
+        // z = conj(fft(conj(Z))) / M, unpacked into even and odd samples
+        let scale = T::from_f64(1.0 / m as f64);
+        for (pair, c) in output.chunks_mut(2).zip(z.iter()) {
+            pair[0] = c.re * scale;
+            pair[1] = -c.im * scale;
+        }
+    }
+}
+
+// half spectrum of sample data
+pub fn rfft<T: Real>(sample: &Sample<T>) -> HalfSpectrum<T> {
+    HalfSpectrum {
+        bins: RealFftPlan::new(sample.data.len()).forward(&sample.data),
+        len: sample.data.len(),
+        rate: sample.rate,
+    }
+}
+
+// half spectrum of windowed sample data
+// bins are not corrected for the window's coherent gain
+pub fn rfft_with<T: Real>(sample: &Sample<T>, window: &Window) -> HalfSpectrum<T> {
+    let mut data = sample.data.clone();
+    window.apply(&mut data);
+    HalfSpectrum {
+        bins: RealFftPlan::new(data.len()).forward(&data),
+        len: data.len(),
+        rate: sample.rate,
+    }
+}
+
+// sample rebuilt from a half spectrum
+pub fn irfft<T: Real>(spectrum: &HalfSpectrum<T>) -> Sample<T> {
+    Sample {
+        data: RealFftPlan::new(spectrum.len).inverse(&spectrum.bins),
+        rate: spectrum.rate,
+    }
+}
+
+// sample of len points synthesized from phasors in the
+// graph_circle convention, each placed on its nearest bin
+// a phasor of magnitude r yields a sine of amplitude 2r, except at
+// dc and nyquist (rate / 2 for even len), whose bins are real: there
+// only the cosine part p.im survives, as a constant or alternating
+// level of p.im, not doubled
+// phasors whose nearest bin is below dc or above nyquist (rate / 2)
+// have no bin in the half spectrum and are skipped
+pub fn from_phasors<T: Real>(phasors: &[Phasor<T>], len: usize, rate: usize) -> Sample<T> {
+    let mut spectrum = HalfSpectrum {
+        bins: vec![Complex::zero(); len / 2 + 1],
+        len,
+        rate,
+    };
+    if len == 0 {
+        spectrum.bins.clear();
+        return irfft(&spectrum);
+    }
+    let df = spectrum.spacing();
+    let last = (spectrum.bins.len() - 1) as f64;
+    for p in phasors {
+        let k = (p.frequency / df).round().as_f64();
+        // also rejects nan frequencies
+        if !(0.0..=last).contains(&k) {
+            continue;
+        }
+        spectrum.bins[k as usize] += transform::phasor_to_bin(p.complex, len);
+    }
+    // dc and nyquist of a real signal are real
+    spectrum.bins[0].im = T::zero();
+    if len.is_multiple_of(2) {
+        spectrum.bins[len / 2].im = T::zero();
+    }
+    irfft(&spectrum)
+}
+
+#[cfg(test)]
+mod tests {
+use std::f32::consts::PI;
+use super::*;
+use tests::complex_signal;
+use util;
+
+#[test]
+    fn test_rfft_matches_fft(){
+        for &n in &[64, 1000, 1009, 2 * 1009] {
+            let data: Vec<f32> = complex_signal::<f32>(n, 0).iter().map(|c| c.re + 0.25).collect();
+            let sample = Sample{ data: data.clone(), rate: 1000 };
+            let half = rfft(&sample);
+            let full = transform::spectrum(&sample);
+
+            assert_eq!(half.bins.len(), n / 2 + 1);
+            for (k, &c) in full.iter().enumerate() {
+                assert!((half.bin(k) - c).norm() < 1e-2, "n = {}, k = {}", n, k);
+            }
+        }
+    }
+
+#[test]
+    fn test_irfft_roundtrip(){
+        for &n in &[64, 1000, 1009] {
+            let data: Vec<f32> = complex_signal::<f32>(n, 0).iter().map(|c| c.re + 0.25).collect();
+            let sample = Sample{ data: data.clone(), rate: 1000 };
+            let restored = irfft(&rfft(&sample));
+
+            assert_eq!(restored.rate, sample.rate);
+            assert_eq!(restored.data.len(), n);
+            for (a, b) in restored.data.iter().zip(data.iter()) {
+                assert!((a - b).abs() < 1e-5, "n = {}", n);
+            }
+        }
+    }
+
+#[test]
+    fn test_real_with_scratch(){
+        for &n in &[1024, 1000, 1009] {
+            let data: Vec<f32> = complex_signal::<f32>(n, 0).iter().map(|c| c.re + 0.25).collect();
+            let plan = RealFftPlan::new(n);
+            let mut bins = vec![Complex::zero(); n / 2 + 1];
+            let mut restored = vec![0.0; n];
+            let mut scratch = vec![Complex::zero(); plan.scratch_len()];
+            let count = ::tests::allocations(|| {
+                plan.forward_with_scratch(&data, &mut bins, &mut scratch);
+                plan.inverse_with_scratch(&bins, &mut restored, &mut scratch);
+            });
+            assert_eq!(count, 0, "n = {}", n);
+            assert_eq!(bins, plan.forward(&data));
+            for (a, b) in restored.iter().zip(data.iter()) {
+                assert!((a - b).abs() < 1e-5, "n = {}", n);
+            }
+        }
+    }
+
+#[test]
+    fn test_from_phasors(){
+        let sine = util::sinewaves(1.0, 1024, &[(5.0,90.0),(60.0,0.0)]);
+        let phasors = vec![
+            Phasor{ frequency: 5.0, complex: Complex::from_polar(&0.5, &(PI / 2.0)) },
+            Phasor{ frequency: 60.0, complex: Complex::from_polar(&0.5, &0.0) },
+        ];
+        let synth = from_phasors(&phasors, sine.data.len(), sine.rate);
+        assert_eq!(synth.data.len(), sine.data.len());
+        for (a, b) in synth.data.iter().zip(sine.data.iter()) {
+            assert!((a - b).abs() < 1e-3);
+        }
+    }
+
+#[test]
+    fn test_from_phasors_out_of_range(){
+        // cosines, a sine has no samples at dc or nyquist
+        let phasor = |frequency: f32| Phasor{ frequency, complex: Complex{ re: 0.0, im: 0.25f32 } };
+        let expected = from_phasors(&[phasor(60.0)], 1024, 1024);
+
+        // negative and above nyquist frequencies do not land in dc or
+        // any other bin
+        for &f in &[-60.0, -1.0, 513.0, 2000.0, f32::NAN] {
+            let synth = from_phasors(&[phasor(60.0), phasor(f)], 1024, 1024);
+            assert_eq!(synth.data, expected.data, "{} hz", f);
+        }
+
+        // dc and nyquist themselves are kept, at p.im and not doubled
+        let dc = from_phasors(&[phasor(0.0)], 1024, 1024);
+        let nyquist = from_phasors(&[phasor(512.0)], 1024, 1024);
+        for (i, (&d, &n)) in dc.data.iter().zip(nyquist.data.iter()).enumerate() {
+            let sign = if i % 2 == 0 { 1.0 } else { -1.0 };
+            assert!((d - 0.25).abs() < 1e-6, "dc {} at {}", d, i);
+            assert!((n - 0.25 * sign).abs() < 1e-6, "nyquist {} at {}", n, i);
+        }
+        // their sine part has nothing to land on
+        let sine = Phasor{ frequency: 0.0, complex: Complex{ re: 0.25f32, im: 0.0 } };
+        assert!(from_phasors(&[sine], 1024, 1024).data.iter().all(|&x| x.abs() < 1e-6));
+    }
+
+#[test]
+    fn test_rfft_frequencies(){
+        let sine = util::sinewaves(1.0, 1000, &[(50.0,0.0)]);
+        let half = rfft(&sine);
+        let peak = half.phasors().into_iter()
+            .fold((0.0, 0.0), |acc: (f32, f32), p| {
+                let r = p.complex.norm();
+                if r > acc.1 { (p.frequency, r) } else { acc }
+            });
+        assert!((peak.0 - 50.0).abs() < half.spacing());
+        assert!((peak.1 - 0.5).abs() < 0.05);
+    }
+}
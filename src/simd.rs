@@ -0,0 +1,291 @@
+// vectorized radix-2 butterflies
+// the kernel is picked at runtime from what the cpu supports,
+// the scalar kernel is the reference the others are tested against
+//
+// complex numbers are #[repr(C)] { re, im } so a slice of them is
+// interleaved re, im pairs that load straight into vector registers
+
+use num_complex::Complex;
+use super::Real;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kernel {
+    Scalar,
+    // 2 f32 or 1 f64 complex per register
+    Sse3,
+    // 4 f32 or 2 f64 complex per register, with fused multiply-add
+    Avx2,
+    // 4 f32 or 2 f64 complex per register
+    Neon,
+}
+
+impl Kernel {
+    // best kernel the running cpu supports
+    pub fn detect() -> Kernel {
+        [Kernel::Avx2, Kernel::Neon, Kernel::Sse3].iter()
+            .cloned()
+            .find(|k| k.is_available())
+            .unwrap_or(Kernel::Scalar)
+    }
+
+    pub fn is_available(&self) -> bool {
+        match *self {
+            Kernel::Scalar => true,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Kernel::Sse3 => is_x86_feature_detected!("sse3"),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Kernel::Avx2 => is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma"),
+            #[cfg(target_arch = "aarch64")]
+            Kernel::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    // every kernel usable on this cpu, scalar first
+    pub fn available() -> Vec<Kernel> {
+        [Kernel::Scalar, Kernel::Sse3, Kernel::Avx2, Kernel::Neon].iter()
+            .cloned()
+            .filter(|k| k.is_available())
+            .collect()
+    }
+}
+
+// one radix-2 stage over blocks of len points
+// twiddles holds e^(-2πik/len) for k in 0..len/2
+pub fn butterflies_scalar<T: Real>(data: &mut [Complex<T>], twiddles: &[Complex<T>], len: usize) {
+    let half = len / 2;
+    for block in data.chunks_mut(len) {
+        let (lo, hi) = block.split_at_mut(half);
+        for ((a, b), &w) in lo.iter_mut().zip(hi.iter_mut()).zip(twiddles.iter()) {
+            let t = *b * w;
+            *b = *a - t;
+            *a += t;
+        }
+    }
+}
+
+// the first two stages after bit reversal as one radix-4 pass,
+// their twiddles are 1 and -i so it needs no multiplications
+pub fn radix4_first<T: Real>(data: &mut [Complex<T>]) {
+    for x in data.chunks_mut(4) {
+        let a0 = x[0] + x[1];
+        let a1 = x[0] - x[1];
+        let a2 = x[2] + x[3];
+        let a3 = x[2] - x[3];
+        // a3 * -i
+        let b3 = Complex{ re: a3.im, im: -a3.re };
+        x[0] = a0 + a2;
+        x[2] = a0 - a2;
+        x[1] = a1 + b3;
+        x[3] = a1 - b3;
+    }
+}
+
+// butterfly stage with the given kernel, falls back to scalar
+// for kernels that are not available
+pub fn butterflies_f32(kernel: Kernel, data: &mut [Complex<f32>], twiddles: &[Complex<f32>], len: usize) {
+    assert!(data.len().is_multiple_of(len) && twiddles.len() >= len / 2);
+    match kernel {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Avx2 if kernel.is_available() => unsafe { x86::avx2_f32(data, twiddles, len) },
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Sse3 if kernel.is_available() => unsafe { x86::sse3_f32(data, twiddles, len) },
+        #[cfg(target_arch = "aarch64")]
+        Kernel::Neon if kernel.is_available() => unsafe { arm::neon_f32(data, twiddles, len) },
+        _ => butterflies_scalar(data, twiddles, len),
+    }
+}
+
+pub fn butterflies_f64(kernel: Kernel, data: &mut [Complex<f64>], twiddles: &[Complex<f64>], len: usize) {
+    assert!(data.len().is_multiple_of(len) && twiddles.len() >= len / 2);
+    match kernel {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Avx2 if kernel.is_available() => unsafe { x86::avx2_f64(data, twiddles, len) },
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Sse3 if kernel.is_available() => unsafe { x86::sse3_f64(data, twiddles, len) },
+        #[cfg(target_arch = "aarch64")]
+        Kernel::Neon if kernel.is_available() => unsafe { arm::neon_f64(data, twiddles, len) },
+        _ => butterflies_scalar(data, twiddles, len),
+    }
+}
+
+// runs a vector body over every block, `width` complex at a time,
+// and finishes the remainder of each half block with scalar code
+// body(a, b, w) gets pointers to the first point of each pair
+#[inline(always)]
+unsafe fn for_each_pair<T: Real, F>(data: &mut [Complex<T>], twiddles: &[Complex<T>], len: usize, width: usize, mut body: F)
+    where F: FnMut(*mut Complex<T>, *mut Complex<T>, *const Complex<T>)
+{
+    let half = len / 2;
+    let vectorized = half - half % width;
+    for block in data.chunks_mut(len) {
+        let p = block.as_mut_ptr();
+        let mut k = 0;
+        while k < vectorized {
+            body(p.add(k), p.add(k + half), twiddles.as_ptr().add(k));
+            k += width;
+        }
+        for k in vectorized..half {
+            let t = block[k + half] * twiddles[k];
+            block[k + half] = block[k] - t;
+            block[k] += t;
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+    use num_complex::Complex;
+    use super::for_each_pair;
+
+    // (br + i bi)(wr + i wi) on interleaved pairs:
+    // b * wr -/+ swap(b) * wi, subtracting in the real lanes
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn avx2_f32(data: &mut [Complex<f32>], twiddles: &[Complex<f32>], len: usize) {
+        for_each_pair(data, twiddles, len, 4, |a, b, w| {
+            let pa = a as *mut f32;
+            let pb = b as *mut f32;
+            let va = _mm256_loadu_ps(pa);
+            let vb = _mm256_loadu_ps(pb);
+            let vw = _mm256_loadu_ps(w as *const f32);
+            let wr = _mm256_moveldup_ps(vw);
+            let wi = _mm256_movehdup_ps(vw);
+            let swapped = _mm256_permute_ps(vb, 0b1011_0001);
+            let t = _mm256_fmaddsub_ps(vb, wr, _mm256_mul_ps(swapped, wi));
+            _mm256_storeu_ps(pa, _mm256_add_ps(va, t));
+            _mm256_storeu_ps(pb, _mm256_sub_ps(va, t));
+        })
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn avx2_f64(data: &mut [Complex<f64>], twiddles: &[Complex<f64>], len: usize) {
+        for_each_pair(data, twiddles, len, 2, |a, b, w| {
+            let pa = a as *mut f64;
+            let pb = b as *mut f64;
+            let va = _mm256_loadu_pd(pa);
+            let vb = _mm256_loadu_pd(pb);
+            let vw = _mm256_loadu_pd(w as *const f64);
+            let wr = _mm256_movedup_pd(vw);
+            let wi = _mm256_permute_pd(vw, 0b1111);
+            let swapped = _mm256_permute_pd(vb, 0b0101);
+            let t = _mm256_fmaddsub_pd(vb, wr, _mm256_mul_pd(swapped, wi));
+            _mm256_storeu_pd(pa, _mm256_add_pd(va, t));
+            _mm256_storeu_pd(pb, _mm256_sub_pd(va, t));
+        })
+    }
+
+    #[target_feature(enable = "sse3")]
+    pub unsafe fn sse3_f32(data: &mut [Complex<f32>], twiddles: &[Complex<f32>], len: usize) {
+        for_each_pair(data, twiddles, len, 2, |a, b, w| {
+            let pa = a as *mut f32;
+            let pb = b as *mut f32;
+            let va = _mm_loadu_ps(pa);
+            let vb = _mm_loadu_ps(pb);
+            let vw = _mm_loadu_ps(w as *const f32);
+            let wr = _mm_moveldup_ps(vw);
+            let wi = _mm_movehdup_ps(vw);
+            let swapped = _mm_shuffle_ps(vb, vb, 0b1011_0001);
+            let t = _mm_addsub_ps(_mm_mul_ps(vb, wr), _mm_mul_ps(swapped, wi));
+            _mm_storeu_ps(pa, _mm_add_ps(va, t));
+            _mm_storeu_ps(pb, _mm_sub_ps(va, t));
+        })
+    }
+
+    #[target_feature(enable = "sse3")]
+    pub unsafe fn sse3_f64(data: &mut [Complex<f64>], twiddles: &[Complex<f64>], len: usize) {
+        for_each_pair(data, twiddles, len, 1, |a, b, w| {
+            let pa = a as *mut f64;
+            let pb = b as *mut f64;
+            let va = _mm_loadu_pd(pa);
+            let vb = _mm_loadu_pd(pb);
+            let vw = _mm_loadu_pd(w as *const f64);
+            let wr = _mm_movedup_pd(vw);
+            let wi = _mm_unpackhi_pd(vw, vw);
+            let swapped = _mm_shuffle_pd(vb, vb, 0b01);
+            let t = _mm_addsub_pd(_mm_mul_pd(vb, wr), _mm_mul_pd(swapped, wi));
+            _mm_storeu_pd(pa, _mm_add_pd(va, t));
+            _mm_storeu_pd(pb, _mm_sub_pd(va, t));
+        })
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arm {
+    use std::arch::aarch64::*;
+    use num_complex::Complex;
+    use super::for_each_pair;
+
+    // vld2 splits interleaved pairs into re and im registers
+    #[target_feature(enable = "neon")]
+    pub unsafe fn neon_f32(data: &mut [Complex<f32>], twiddles: &[Complex<f32>], len: usize) {
+        for_each_pair(data, twiddles, len, 4, |a, b, w| {
+            let pa = a as *mut f32;
+            let pb = b as *mut f32;
+            let va = vld2q_f32(pa);
+            let vb = vld2q_f32(pb);
+            let vw = vld2q_f32(w as *const f32);
+            let re = vfmsq_f32(vmulq_f32(vb.0, vw.0), vb.1, vw.1);
+            let im = vfmaq_f32(vmulq_f32(vb.0, vw.1), vb.1, vw.0);
+            vst2q_f32(pa, float32x4x2_t(vaddq_f32(va.0, re), vaddq_f32(va.1, im)));
+            vst2q_f32(pb, float32x4x2_t(vsubq_f32(va.0, re), vsubq_f32(va.1, im)));
+        })
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn neon_f64(data: &mut [Complex<f64>], twiddles: &[Complex<f64>], len: usize) {
+        for_each_pair(data, twiddles, len, 2, |a, b, w| {
+            let pa = a as *mut f64;
+            let pb = b as *mut f64;
+            let va = vld2q_f64(pa);
+            let vb = vld2q_f64(pb);
+            let vw = vld2q_f64(w as *const f64);
+            let re = vfmsq_f64(vmulq_f64(vb.0, vw.0), vb.1, vw.1);
+            let im = vfmaq_f64(vmulq_f64(vb.0, vw.1), vb.1, vw.0);
+            vst2q_f64(pa, float64x2x2_t(vaddq_f64(va.0, re), vaddq_f64(va.1, im)));
+            vst2q_f64(pb, float64x2x2_t(vsubq_f64(va.0, re), vsubq_f64(va.1, im)));
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+use super::*;
+use tests::complex_signal;
+use transform::cis;
+
+fn stage_twiddles<T: Real>(len: usize) -> Vec<Complex<T>> {
+    (0..len / 2).map(|k| cis(-2.0 * std::f64::consts::PI * k as f64 / len as f64)).collect()
+}
+
+#[test]
+    fn test_kernels_match_scalar(){
+        assert!(Kernel::detect().is_available());
+        // len 6 leaves a remainder past the vector width
+        for &len in &[2, 6, 8, 16, 64, 256] {
+            let mut expected32 = complex_signal::<f32>(1024 / len * len, 0);
+            let mut expected64 = complex_signal::<f64>(1024 / len * len, 0);
+            let tw32 = stage_twiddles(len);
+            let tw64 = stage_twiddles(len);
+            butterflies_scalar(&mut expected32, &tw32, len);
+            butterflies_scalar(&mut expected64, &tw64, len);
+
+            for &kernel in &Kernel::available() {
+                let mut data32 = complex_signal::<f32>(expected32.len(), 0);
+                let mut data64 = complex_signal::<f64>(expected64.len(), 0);
+                butterflies_f32(kernel, &mut data32, &tw32, len);
+                butterflies_f64(kernel, &mut data64, &tw64, len);
+                for (a, b) in data32.iter().zip(expected32.iter()) {
+                    assert!((a - b).norm() < 1e-5, "{:?} len {}", kernel, len);
+                }
+                for (a, b) in data64.iter().zip(expected64.iter()) {
+                    assert!((a - b).norm() < 1e-12, "{:?} len {}", kernel, len);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,289 @@
+// short-time fourier transform
+
+use std::error::Error;
+use std::fmt;
+use num_complex::Complex;
+use super::{Sample, Real};
+use real::RealFftPlan;
+use parallel;
+use window::Window;
+
+// time x frequency matrix of half spectra
+pub struct Spectrogram<T = f32> {
+    // frames[i][k]: bin k of frame i
+    pub frames: Vec<Vec<Complex<T>>>,
+    // frame center times, seconds
+    pub times: Vec<T>,
+    // bin frequencies, hz
+    pub frequencies: Vec<T>,
+    pub frame_len: usize,
+    pub hop: usize,
+    pub window: Window,
+    // length and samplerate of the analyzed sample
+    pub len: usize,
+    pub rate: usize,
+}
+
+impl<T: Real> Spectrogram<T> {
+    // |X| per frame and bin
+    pub fn magnitude(&self) -> Vec<Vec<T>> {
+        self.frames.iter()
+            .map(|frame| frame.iter().map(|c| c.norm()).collect())
+            .collect()
+    }
+}
+
+// splits sample into windowed frames of frame_len, hop samples apart,
+// and transforms each of them
+// frames are centered on i * hop with the sample zero padded by
+// frame_len / 2 on both ends, so every sample is covered
+pub fn stft<T: Real>(sample: &Sample<T>, frame_len: usize, hop: usize, window: &Window) -> Spectrogram<T> {
+    stft_with(sample, frame_len, hop, window, 1)
+}
+
+// stft with frames transformed on up to threads threads
+pub fn stft_with<T: Real>(sample: &Sample<T>, frame_len: usize, hop: usize, window: &Window, threads: usize) -> Spectrogram<T> {
+    assert!(frame_len > 0, "stft frame length must be positive");
+    assert!(hop > 0, "stft hop must be positive");
+
+    let len = sample.data.len();
+    let pad = frame_len / 2;
+    let count = if len == 0 { 0 } else { len / hop + 1 };
+
+    let plan = RealFftPlan::new(frame_len);
+    let coefficients: Vec<T> = window.coefficients(frame_len);
+
+    let indices: Vec<usize> = (0..count).collect();
+    let frames = parallel::map(&indices, threads, |&i| {
+        // sample index of frame start, may be negative
+        let start = (i * hop) as isize - pad as isize;
+        let frame: Vec<T> = (0..frame_len)
+            .map(|j| {
+                let idx = start + j as isize;
+                if idx >= 0 && (idx as usize) < len {
+                    sample.data[idx as usize] * coefficients[j]
+                } else {
+                    T::zero()
+                }
+            })
+            .collect();
+        plan.forward(&frame)
+    });
+
+    Spectrogram {
+        frames,
+        times: (0..count).map(|i| T::from_f64((i * hop) as f64 / sample.rate as f64)).collect(),
+        frequencies: (0..frame_len / 2 + 1)
+            .map(|k| T::from_f64(k as f64 * sample.rate as f64 / frame_len as f64))
+            .collect(),
+        frame_len,
+        hop,
+        window: *window,
+        len,
+        rate: sample.rate,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StftError {
+    // squared window does not overlap-add to a constant at this hop
+    NotCola { window: Window, frame_len: usize, hop: usize },
+    // frame has wrong number of bins for the frame length
+    FrameSize { frame: usize, expected: usize, actual: usize },
+}
+
+impl fmt::Display for StftError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StftError::NotCola { ref window, frame_len, hop } =>
+                write!(f, "{} window of {} samples is not COLA at hop {}", window, frame_len, hop),
+            StftError::FrameSize { frame, expected, actual } =>
+                write!(f, "frame {} has {} bins, expected {}", frame, actual, expected),
+        }
+    }
+}
+
+impl Error for StftError {}
+
+// checks the constant overlap-add condition for weighted overlap-add:
+// the squared window shifted by multiples of hop must sum to a constant,
+// since it is applied once on analysis and once on synthesis
+pub fn is_cola(window: &Window, frame_len: usize, hop: usize) -> bool {
+    if frame_len == 0 || hop == 0 || hop > frame_len {
+        return false;
+    }
+    let coefficients: Vec<f64> = window.coefficients(frame_len);
+    let envelope: Vec<f64> = (0..hop)
+        .map(|n| {
+            coefficients.iter().skip(n).step_by(hop).map(|w| w * w).sum()
+        })
+        .collect();
+    let max = envelope.iter().cloned().fold(0.0, f64::max);
+    let min = envelope.iter().cloned().fold(max, f64::min);
+    max > 0.0 && (max - min) <= max * 1e-4
+}
+
+// rebuilds a sample from possibly modified stft frames with
+// weighted overlap-add
+// each frame is inverse transformed, windowed again and summed,
+// then divided by the summed squared window
+pub fn istft<T: Real>(spec: &Spectrogram<T>) -> Result<Sample<T>, StftError> {
+    istft_with(spec, 1)
+}
+
+// istft with frames inverse transformed on up to threads threads,
+// the overlap-add itself stays serial
+pub fn istft_with<T: Real>(spec: &Spectrogram<T>, threads: usize) -> Result<Sample<T>, StftError> {
+    let frame_len = spec.frame_len;
+    if !is_cola(&spec.window, frame_len, spec.hop) {
+        return Err(StftError::NotCola { window: spec.window, frame_len, hop: spec.hop });
+    }
+
+    let plan = RealFftPlan::new(frame_len);
+    let coefficients: Vec<T> = spec.window.coefficients(frame_len);
+    let pad = frame_len / 2;
+
+    // output and window envelope over the padded signal
+    let padded = (spec.frames.len().saturating_sub(1)) * spec.hop + frame_len;
+    let mut data = vec![T::zero(); padded.max(spec.len + 2 * pad)];
+    let mut envelope = vec![T::zero(); data.len()];
+
+    if let Some((i, bins)) = spec.frames.iter().enumerate().find(|&(_, bins)| bins.len() != frame_len / 2 + 1) {
+        return Err(StftError::FrameSize { frame: i, expected: frame_len / 2 + 1, actual: bins.len() });
+    }
+    let frames = parallel::map(&spec.frames, threads, |bins| plan.inverse(bins));
+
+    for (i, frame) in frames.iter().enumerate() {
+        let start = i * spec.hop;
+        for (j, (&x, &w)) in frame.iter().zip(coefficients.iter()).enumerate() {
+            data[start + j] += x * w;
+            envelope[start + j] += w * w;
+        }
+    }
+
+    // envelope only drops near the padded ends
+    let data = data.iter()
+        .zip(envelope.iter())
+        .skip(pad)
+        .take(spec.len)
+        .map(|(&x, &e)| if e > T::from_f64(1e-6) { x / e } else { T::zero() })
+        .collect();
+
+    Ok(Sample{ data, rate: spec.rate })
+}
+
+#[cfg(test)]
+mod tests {
+use super::*;
+use util;
+
+// index of the strongest bin
+fn peak(frame: &[f32]) -> usize {
+    frame.iter()
+        .enumerate()
+        .fold((0, 0.0), |acc, (k, &m)| if m > acc.1 { (k, m) } else { acc })
+        .0
+}
+
+#[test]
+    fn test_stft_axes(){
+        let sine = util::sinewave(125.0, 0.0, 1.0, 1000, 1.0);
+        let spec = stft(&sine, 256, 64, &Window::Hann);
+
+        assert_eq!(spec.frames.len(), spec.times.len());
+        assert_eq!(spec.frames.len(), sine.data.len() / 64 + 1);
+        assert_eq!(spec.frequencies.len(), 129);
+        assert_eq!(spec.times[1], 0.064);
+        assert_eq!(spec.frequencies[32], 125.0);
+
+        // stationary tone sits in the same bin away from the edges
+        let mag = spec.magnitude();
+        for frame in &mag[2..mag.len() - 2] {
+            assert_eq!(peak(frame), 32);
+        }
+    }
+
+#[test]
+    fn test_cola(){
+        assert!(is_cola(&Window::Rectangular, 256, 256));
+        assert!(is_cola(&Window::Rectangular, 256, 64));
+        assert!(is_cola(&Window::Hann, 256, 64));
+        assert!(!is_cola(&Window::Hann, 256, 128));
+        assert!(!is_cola(&Window::Hann, 256, 100));
+        assert!(!is_cola(&Window::Hann, 256, 512));
+        assert!(!is_cola(&Window::Blackman, 300, 100));
+        assert!(is_cola(&Window::Blackman, 300, 60));
+    }
+
+#[test]
+    fn test_istft_roundtrip(){
+        let sample: Sample = util::sinewaves(1.0, 1000, &[(50.0,0.0),(123.0,45.0)]);
+        for &(window, frame_len, hop) in &[(Window::Hann, 256, 64), (Window::Rectangular, 100, 100), (Window::Blackman, 300, 60)] {
+            let restored = istft(&stft(&sample, frame_len, hop, &window)).unwrap();
+            assert_eq!(restored.rate, sample.rate);
+            assert_eq!(restored.data.len(), sample.data.len());
+            for (a, b) in restored.data.iter().zip(sample.data.iter()) {
+                assert!((a - b).abs() < 1e-4, "{} {}/{}", window, frame_len, hop);
+            }
+        }
+    }
+
+#[test]
+    fn test_istft_edit(){
+        // notch the 123 hz tone out of every frame
+        let sample = util::sinewaves(1.0, 1000, &[(50.0,0.0),(123.0,45.0)]);
+        let mut spec = stft(&sample, 200, 50, &Window::Hann);
+        let notch = spec.frequencies.iter().position(|&f| f > 110.0).unwrap();
+        for frame in spec.frames.iter_mut() {
+            for c in frame[notch..notch + 6].iter_mut() {
+                *c = Complex{ re: 0.0, im: 0.0 };
+            }
+        }
+        let filtered = istft(&spec).unwrap();
+        let expected: Sample = util::sinewave(50.0, 0.0, 1.0, 1000, 1.0);
+        // compare away from the edges
+        for (a, b) in filtered.data.iter().zip(expected.data.iter()).skip(100).take(800) {
+            assert!((a - b).abs() < 0.01);
+        }
+    }
+
+#[test]
+    fn test_istft_errors(){
+        let sample = util::sinewave(50.0, 0.0, 1.0, 1000, 1.0);
+        let mut spec = stft(&sample, 256, 128, &Window::Hann);
+        assert_eq!(istft(&spec).err(),
+                   Some(StftError::NotCola{ window: Window::Hann, frame_len: 256, hop: 128 }));
+
+        spec.hop = 64;
+        spec.frames[3].pop();
+        assert_eq!(istft(&spec).err(),
+                   Some(StftError::FrameSize{ frame: 3, expected: 129, actual: 128 }));
+    }
+
+#[test]
+    fn test_stft_threads(){
+        let sample: Sample = util::sinewaves(1.0, 1000, &[(50.0,0.0),(123.0,45.0)]);
+        let serial = stft(&sample, 256, 64, &Window::Hann);
+        let threaded = stft_with(&sample, 256, 64, &Window::Hann, 4);
+        assert_eq!(serial.frames, threaded.frames);
+        assert_eq!(istft(&serial).unwrap().data, istft_with(&threaded, 4).unwrap().data);
+    }
+
+#[test]
+    fn test_stft_tracks_change(){
+        // 50 hz for the first half second, 200 hz after
+        let mut sample = util::sinewave(50.0, 0.0, 0.5, 1000, 1.0);
+        sample.data.extend(util::sinewave(200.0, 0.0, 0.5, 1000, 1.0).data);
+        let spec = stft(&sample, 100, 50, &Window::Hann);
+        let mag = spec.magnitude();
+
+        for (t, frame) in spec.times.iter().zip(mag.iter()) {
+            let f = spec.frequencies[peak(frame)];
+            if *t < 0.4 {
+                assert_eq!(f, 50.0, "at {} s", t);
+            } else if *t > 0.6 && *t < 0.9 {
+                assert_eq!(f, 200.0, "at {} s", t);
+            }
+        }
+    }
+}
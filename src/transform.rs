@@ -0,0 +1,643 @@
+// fast fourier transform core
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use num_complex::Complex;
+use num_traits::Zero;
+use super::{Sample, Real};
+use simd::{self, Kernel};
+
+pub fn is_pow2(n: usize) -> bool {
+    n != 0 && n & (n - 1) == 0
+}
+
+// largest prime factor handled by mixed radix,
+// lengths with bigger factors go through bluestein
+const MAX_RADIX: usize = 31;
+
+// e^(iφ), evaluated in f64 whatever the target precision
+pub fn cis<T: Real>(phi: f64) -> Complex<T> {
+    Complex{ re: T::from_f64(phi.cos()), im: T::from_f64(phi.sin()) }
+}
+
+// e^(-2πik/n)
+fn twiddle<T: Real>(k: usize, n: usize) -> Complex<T> {
+    // reduce in f64 so large k*j products keep their precision
+    cis(-2.0 * std::f64::consts::PI * (k % n) as f64 / n as f64)
+}
+
+// prime factors of n in ascending order
+pub fn factorize(mut n: usize) -> Vec<usize> {
+    let mut factors = vec![];
+    let mut p = 2;
+    while p * p <= n {
+        while n.is_multiple_of(p) {
+            factors.push(p);
+            n /= p;
+        }
+        p += 1;
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+    factors
+}
+
+enum Algorithm<T> {
+    // nothing to do for lengths 0 and 1
+    Identity,
+    // bit reversal permutation, swaps only where i < j, and the
+    // twiddles of every stage stored contiguously for vector loads,
+    // the stage of length len starts at offset len/2 - 1
+    Radix2 { bitrev: Vec<(usize, usize)>, stages: Vec<Complex<T>> },
+    MixedRadix { factors: Vec<usize> },
+    // chirp and transformed convolution kernel over an inner
+    // power of two plan
+    Bluestein { chirp: Vec<Complex<T>>, kernel: Vec<Complex<T>>, inner: Box<FftPlan<T>> },
+}
+
+// precomputed transform for a fixed length
+// twiddle factors and permutation tables are built once in new()
+// so repeated transforms of the same size skip the setup cost
+pub struct FftPlan<T = f32> {
+    len: usize,
+    // e^(-2πik/len) for k in 0..len
+    twiddles: Vec<Complex<T>>,
+    algorithm: Algorithm<T>,
+    // butterfly kernel for the radix-2 stages
+    kernel: Kernel,
+}
+
+impl<T: Real> FftPlan<T> {
+    // plan using the fastest butterfly kernel the cpu supports
+    pub fn new(len: usize) -> FftPlan<T> {
+        FftPlan::with_kernel(len, Kernel::detect())
+    }
+
+    // plan using the given butterfly kernel,
+    // eg. Kernel::Scalar to compare against the vectorized ones
+    pub fn with_kernel(len: usize, kernel: Kernel) -> FftPlan<T> {
+        assert!(kernel.is_available(), "{:?} kernel is not supported on this cpu", kernel);
+        let algorithm = if len <= 1 {
+            Algorithm::Identity
+        } else if is_pow2(len) {
+            Algorithm::Radix2 { bitrev: bit_reverse_table(len), stages: stage_twiddles(len) }
+        } else {
+            let factors = factorize(len);
+            if factors.iter().all(|&p| p <= MAX_RADIX) {
+                Algorithm::MixedRadix { factors }
+            } else {
+                bluestein_setup(len, kernel)
+            }
+        };
+
+        // radix-2 keeps its own stage tables and
+        // bluestein does all its work in the inner plan
+        let twiddles = match algorithm {
+            Algorithm::MixedRadix { .. } => (0..len).map(|k| twiddle(k, len)).collect(),
+            _ => vec![],
+        };
+
+        FftPlan { len, twiddles, algorithm, kernel }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn kernel(&self) -> Kernel {
+        self.kernel
+    }
+
+    // in-place forward transform
+    // X[k] = sum x[n] * e^(-2πikn/N)
+    // allocates scratch space when the algorithm needs it,
+    // see process_with_scratch
+    pub fn process(&self, data: &mut [Complex<T>]) {
+        let mut scratch = vec![Complex::zero(); self.scratch_len()];
+        self.process_with_scratch(data, &mut scratch);
+    }
+
+    // scratch length process_with_scratch needs, 0 for powers of two
+    pub fn scratch_len(&self) -> usize {
+        match self.algorithm {
+            Algorithm::Identity | Algorithm::Radix2 { .. } => 0,
+            Algorithm::MixedRadix { .. } => self.len,
+            Algorithm::Bluestein { ref inner, .. } => inner.len() + inner.scratch_len(),
+        }
+    }
+
+    // in-place forward transform that never allocates,
+    // scratch must hold at least scratch_len() points
+    pub fn process_with_scratch(&self, data: &mut [Complex<T>], scratch: &mut [Complex<T>]) {
+        assert_eq!(data.len(), self.len, "plan length {} does not match data length {}",
+                   self.len, data.len());
+        assert!(scratch.len() >= self.scratch_len(), "scratch of {} points, plan needs {}",
+                scratch.len(), self.scratch_len());
+        match self.algorithm {
+            Algorithm::Identity => {}
+            Algorithm::Radix2 { ref bitrev, ref stages } => self.radix2(data, bitrev, stages),
+            Algorithm::MixedRadix { ref factors } => {
+                let input = &mut scratch[..self.len];
+                input.copy_from_slice(data);
+                self.mixed_radix(input, 1, data, factors, 1);
+            }
+            Algorithm::Bluestein { ref chirp, ref kernel, ref inner } => {
+                bluestein(data, scratch, chirp, kernel, inner)
+            }
+        }
+    }
+
+    // in-place inverse transform, scaled by 1/N
+    // so that inverse(process(x)) == x
+    pub fn inverse(&self, data: &mut [Complex<T>]) {
+        let mut scratch = vec![Complex::zero(); self.scratch_len()];
+        self.inverse_with_scratch(data, &mut scratch);
+    }
+
+    // inverse transform that never allocates
+    pub fn inverse_with_scratch(&self, data: &mut [Complex<T>], scratch: &mut [Complex<T>]) {
+        // ifft(x) = conj(fft(conj(x))) / N
+        for c in data.iter_mut() {
+            *c = c.conj();
+        }
+        self.process_with_scratch(data, scratch);
+        let scale = T::from_f64(1.0 / self.len.max(1) as f64);
+        for c in data.iter_mut() {
+            *c = c.conj() * scale;
+        }
+    }
+
+    // iterative radix-2 cooley-tukey
+    // the first two stages run as one multiplication free radix-4
+    // pass, the rest through the plan's butterfly kernel
+    fn radix2(&self, data: &mut [Complex<T>], bitrev: &[(usize, usize)], stages: &[Complex<T>]) {
+        let n = self.len;
+        for &(i, j) in bitrev {
+            data.swap(i, j);
+        }
+
+        let mut len = if n >= 4 {
+            simd::radix4_first(data);
+            8
+        } else {
+            2
+        };
+        while len <= n {
+            let half = len / 2;
+            T::butterflies(self.kernel, data, &stages[half - 1..len - 1], len);
+            len <<= 1;
+        }
+    }
+
+    // recursive decimation in time over the given factors
+    // transforms every step:th point of input into out
+    // stride maps sub-transform twiddles into the full length table
+    fn mixed_radix(&self, input: &[Complex<T>], step: usize, out: &mut [Complex<T>], factors: &[usize], stride: usize) {
+        let n = out.len();
+        if n == 1 {
+            out[0] = input[0];
+            return;
+        }
+        let p = factors[0];
+        let m = n / p;
+
+        // transform every p:th element starting from offset r
+        // into out[r*m..(r+1)*m]
+        for (r, sub) in out.chunks_mut(m).enumerate() {
+            self.mixed_radix(&input[r * step..], step * p, sub, &factors[1..], stride * p);
+        }
+
+        // combine p transforms of length m with a size p dft
+        // point k of every sub transform maps to outputs k + m*q,
+        // the same positions, so each group is combined in place
+        let mut group = [Complex::zero(); MAX_RADIX];
+        for k in 0..m {
+            for (r, g) in group.iter_mut().enumerate().take(p) {
+                *g = out[r * m + k];
+            }
+            for q in 0..p {
+                let idx = k + m * q;
+                out[idx] = group[..p].iter().enumerate().fold(Complex::zero(), |acc, (r, &g)| {
+                    acc + g * self.twiddles[(r * idx) % n * stride]
+                });
+            }
+        }
+    }
+}
+
+// index pairs to swap into bit-reversed order
+fn bit_reverse_table(n: usize) -> Vec<(usize, usize)> {
+    let mut table = vec![];
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            table.push((i, j));
+        }
+    }
+    table
+}
+
+// e^(-2πik/len) for k in 0..len/2, for every stage len = 2, 4, .., n
+fn stage_twiddles<T: Real>(n: usize) -> Vec<Complex<T>> {
+    let mut stages = Vec::with_capacity(n - 1);
+    let mut len = 2;
+    while len <= n {
+        stages.extend((0..len / 2).map(|k| twiddle::<T>(k, len)));
+        len <<= 1;
+    }
+    stages
+}
+
+// bluestein's chirp-z expresses the dft as a convolution
+// which is evaluated with power of two transforms
+fn bluestein_setup<T: Real>(n: usize, kernel: Kernel) -> Algorithm<T> {
+    let m = (2 * n - 1).next_power_of_two();
+    let inner = FftPlan::with_kernel(m, kernel);
+
+    // chirp e^(-πij²/n), j² reduced modulo 2n
+    let chirp: Vec<Complex<T>> = (0..n)
+        .map(|j| {
+            cis(-std::f64::consts::PI * ((j * j) % (2 * n)) as f64 / n as f64)
+        })
+        .collect();
+
+    let mut kernel = vec![Complex::zero(); m];
+    kernel[0] = chirp[0].conj();
+    for j in 1..n {
+        kernel[j] = chirp[j].conj();
+        kernel[m - j] = chirp[j].conj();
+    }
+    inner.process(&mut kernel);
+
+    Algorithm::Bluestein { chirp, kernel, inner: Box::new(inner) }
+}
+
+fn bluestein<T: Real>(data: &mut [Complex<T>], scratch: &mut [Complex<T>], chirp: &[Complex<T>], kernel: &[Complex<T>], inner: &FftPlan<T>) {
+    let n = data.len();
+    let m = inner.len();
+
+    let (a, rest) = scratch.split_at_mut(m);
+    for j in 0..m {
+        a[j] = if j < n { data[j] * chirp[j] } else { Complex::zero() };
+    }
+
+    inner.process_with_scratch(a, rest);
+    for (x, y) in a.iter_mut().zip(kernel.iter()) {
+        *x = (*x * *y).conj();
+    }
+    // inverse through conjugated forward transform
+    inner.process_with_scratch(a, rest);
+
+    let scale = T::from_f64(1.0 / m as f64);
+    for k in 0..n {
+        data[k] = a[k].conj() * scale * chirp[k];
+    }
+}
+
+// caches plans by length so callers transforming many
+// frames of a few sizes share the setup work
+pub struct Planner<T = f32> {
+    plans: HashMap<usize, Arc<FftPlan<T>>>,
+}
+
+impl<T: Real> Default for Planner<T> {
+    fn default() -> Planner<T> {
+        Planner { plans: HashMap::new() }
+    }
+}
+
+impl<T: Real> Planner<T> {
+    pub fn new() -> Planner<T> {
+        Planner::default()
+    }
+
+    // returns a cached plan for len, building it on first use
+    pub fn plan(&mut self, len: usize) -> Arc<FftPlan<T>> {
+        self.plans.entry(len)
+            .or_insert_with(|| Arc::new(FftPlan::new(len)))
+            .clone()
+    }
+}
+
+// in-place discrete fourier transform of any length
+// power of two lengths use radix-2, smooth lengths mixed radix
+// and lengths with large prime factors bluestein's chirp-z
+// builds a throwaway plan, use FftPlan for repeated transforms
+pub fn fft<T: Real>(data: &mut [Complex<T>]) {
+    FftPlan::new(data.len()).process(data);
+}
+
+// in-place inverse transform of any length, scaled by 1/N
+pub fn ifft<T: Real>(data: &mut [Complex<T>]) {
+    FftPlan::new(data.len()).inverse(data);
+}
+
+// chirp-z transform: evaluates the dft at m points
+// w0, w0 + dw, ..., w0 + (m-1)dw in radians per sample
+// X[k] = sum x[n] * e^(-i(w0 + k dw)n)
+// uses bluestein's identity nk = (n² + k² - (k-n)²) / 2 to turn
+// the sum into a convolution, so the cost is O((n+m) log(n+m))
+// for any spacing instead of O(nm)
+pub fn czt<T: Real>(data: &[Complex<T>], m: usize, w0: f64, dw: f64) -> Vec<Complex<T>> {
+    let n = data.len();
+    if n == 0 || m == 0 {
+        return vec![Complex::zero(); m];
+    }
+    let len = (n + m - 1).next_power_of_two();
+    let plan = FftPlan::new(len);
+
+    // e^(-i dw j² / 2), phase reduced in f64 to survive large j²
+    let chirp = |j: usize| -> Complex<f64> {
+        let phi = (dw * (j as f64) * (j as f64) / 2.0) % (2.0 * std::f64::consts::PI);
+        Complex::from_polar(&1.0, &-phi)
+    };
+    let narrow = |c: Complex<f64>| Complex{ re: T::from_f64(c.re), im: T::from_f64(c.im) };
+
+    // x[n] e^(-i w0 n) chirp[n]
+    let mut a = vec![Complex::zero(); len];
+    for (j, &x) in data.iter().enumerate() {
+        let shift = Complex::from_polar(&1.0, &(-(w0 * j as f64) % (2.0 * std::f64::consts::PI)));
+        a[j] = x * narrow(shift * chirp(j));
+    }
+
+    // conj(chirp) at lags -(n-1)..m-1, wrapped around len
+    let mut b = vec![Complex::zero(); len];
+    for (j, c) in b.iter_mut().enumerate().take(m) {
+        *c = narrow(chirp(j).conj());
+    }
+    for j in 1..n {
+        b[len - j] = narrow(chirp(j).conj());
+    }
+
+    plan.process(&mut a);
+    plan.process(&mut b);
+    for (x, y) in a.iter_mut().zip(b.iter()) {
+        *x *= *y;
+    }
+    plan.inverse(&mut a);
+
+    (0..m).map(|k| a[k] * narrow(chirp(k))).collect()
+}
+
+// zoom fft of sample data over count frequencies
+// min, min + ss, ..., in the winding machine convention
+pub fn zoom<T: Real>(sample: &Sample<T>, min: T, ss: T, count: usize) -> Vec<Complex<T>> {
+    let data: Vec<Complex<T>> = sample.data.iter()
+        .map(|&a| Complex{ re: a, im: T::zero() })
+        .collect();
+    let scale = 2.0 * std::f64::consts::PI / sample.rate as f64;
+    czt(&data, count, min.as_f64() * scale, ss.as_f64() * scale)
+        .into_iter()
+        .map(|c| bin_to_phasor(c, data.len()))
+        .collect()
+}
+
+// returns full bin spectrum of sample data
+// bin k lies at frequency k * rate / N
+pub fn spectrum<T: Real>(sample: &Sample<T>) -> Vec<Complex<T>> {
+    spectrum_with(&FftPlan::new(sample.data.len()), sample)
+}
+
+// spectrum using a prepared plan of sample length
+pub fn spectrum_with<T: Real>(plan: &FftPlan<T>, sample: &Sample<T>) -> Vec<Complex<T>> {
+    let mut data: Vec<Complex<T>> = sample.data.iter()
+        .map(|&a| Complex{ re: a, im: T::zero() })
+        .collect();
+    plan.process(&mut data);
+    data
+}
+
+// frequency spacing between two bins, hz
+pub fn bin_spacing<T: Real>(sample: &Sample<T>) -> T {
+    T::from_f64(sample.rate as f64 / sample.data.len() as f64)
+}
+
+// converts a raw dft bin into the winding machine convention
+// used by graph_circle: mean vector, sine phase as angle
+pub fn bin_to_phasor<T: Real>(c: Complex<T>, n: usize) -> Complex<T> {
+    Complex{ re: -c.im, im: c.re } / T::from_f64(n as f64)
+}
+
+// inverse of bin_to_phasor
+pub fn phasor_to_bin<T: Real>(p: Complex<T>, n: usize) -> Complex<T> {
+    Complex{ re: p.im, im: -p.re } * T::from_f64(n as f64)
+}
+
+// sample rebuilt from a full spectrum of sample length
+// imaginary residue of non-hermitian spectra is dropped
+pub fn resynthesize<T: Real>(bins: &[Complex<T>], rate: usize) -> Sample<T> {
+    let mut data = bins.to_vec();
+    ifft(&mut data);
+    Sample {
+        data: data.into_iter().map(|c| c.re).collect(),
+        rate,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+use std::f32::consts::PI;
+use super::*;
+use tests::complex_signal;
+use util;
+
+// naive O(n²) reference dft
+fn dft<T: Real>(data: &[Complex<T>]) -> Vec<Complex<T>> {
+    let n = data.len();
+    (0..n).map(|k| {
+        data.iter().enumerate().fold(Complex::zero(), |acc, (j, &x)| {
+            let phi = -2.0 * std::f64::consts::PI * ((k * j) % n) as f64 / n as f64;
+            acc + x * Complex::from_polar(&T::one(), &T::from_f64(phi))
+        })
+    }).collect()
+}
+
+#[test]
+    fn test_fft_matches_dft(){
+        let data: Vec<Complex<f32>> = complex_signal(64, 0);
+        let expected = dft(&data);
+        let mut actual = data.clone();
+        fft(&mut actual);
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).norm() < 1e-3);
+        }
+    }
+
+#[test]
+    fn test_fft_any_length(){
+        // smooth, prime and mixed with a large prime factor
+        for &n in &[12, 1000, 97, 1009, 2 * 3 * 37] {
+            let data: Vec<Complex<f32>> = complex_signal(n, 0);
+            let expected = dft(&data);
+            let mut actual = data.clone();
+            fft(&mut actual);
+            assert_eq!(actual.len(), n);
+            for (a, e) in actual.iter().zip(expected.iter()) {
+                assert!((a - e).norm() < 1e-2 * (n as f32).sqrt(), "n = {}", n);
+            }
+        }
+    }
+
+#[test]
+    fn test_fft_f64(){
+        for &n in &[64, 1000, 1009] {
+            let data: Vec<Complex<f64>> = complex_signal(n, 0);
+            let mut actual = data.clone();
+            fft(&mut actual);
+            ifft(&mut actual);
+            for (a, e) in actual.iter().zip(data.iter()) {
+                assert!((a - e).norm() < 1e-12, "n = {}", n);
+            }
+        }
+    }
+
+#[test]
+    fn test_plan_reuse(){
+        let mut planner = Planner::new();
+        let plan = planner.plan(1009);
+        assert!(Arc::ptr_eq(&plan, &planner.plan(1009)));
+        let mut planner64 = Planner::new();
+        let plan64 = planner64.plan(1009);
+
+        // the shared plan keeps matching a fresh one and the reference
+        for shift in 0..3 {
+            let data: Vec<Complex<f32>> = complex_signal(1009, shift);
+            let mut expected = data.clone();
+            FftPlan::new(1009).process(&mut expected);
+            let mut actual = data.clone();
+            plan.process(&mut actual);
+            for (a, e) in actual.iter().zip(expected.iter()) {
+                assert!((a - e).norm() < 1e-4, "shift {}", shift);
+            }
+
+            let data: Vec<Complex<f64>> = complex_signal(1009, shift);
+            let expected = dft(&data);
+            let mut actual = data.clone();
+            plan64.process(&mut actual);
+            for (a, e) in actual.iter().zip(expected.iter()) {
+                assert!((a - e).norm() < 1e-9, "shift {}", shift);
+            }
+        }
+    }
+
+#[test]
+    fn test_kernels_match_scalar(){
+        // 3000 goes through bluestein with a radix-2 inner plan
+        for &n in &[2, 4, 8, 1024, 3000] {
+            let data: Vec<Complex<f32>> = complex_signal(n, 0);
+            let mut expected = data.clone();
+            FftPlan::with_kernel(n, Kernel::Scalar).process(&mut expected);
+            for &kernel in &Kernel::available() {
+                let plan = FftPlan::with_kernel(n, kernel);
+                assert_eq!(plan.kernel(), kernel);
+                let mut actual = data.clone();
+                plan.process(&mut actual);
+                for (a, e) in actual.iter().zip(expected.iter()) {
+                    assert!((a - e).norm() < 1e-3, "{:?} n = {}", kernel, n);
+                }
+            }
+        }
+    }
+
+#[test]
+    fn test_process_with_scratch(){
+        // radix-2, mixed radix and bluestein
+        for &n in &[1024, 1000, 1009] {
+            let data: Vec<Complex<f32>> = complex_signal(n, 0);
+            let plan = FftPlan::new(n);
+            let mut expected = data.clone();
+            plan.process(&mut expected);
+
+            let mut actual = data.clone();
+            let mut scratch = vec![Complex::zero(); plan.scratch_len()];
+            let count = ::tests::allocations(|| {
+                plan.process_with_scratch(&mut actual, &mut scratch);
+            });
+            assert_eq!(count, 0, "n = {}", n);
+            assert_eq!(actual, expected);
+
+            assert_eq!(::tests::allocations(|| plan.inverse_with_scratch(&mut actual, &mut scratch)), 0);
+            for (a, b) in actual.iter().zip(data.iter()) {
+                assert!((a - b).norm() < 1e-4, "n = {}", n);
+            }
+        }
+    }
+
+#[test]
+    fn test_ifft_roundtrip(){
+        for &n in &[64, 1000, 1009] {
+            let data: Vec<Complex<f32>> = complex_signal(n, 0);
+            let mut actual = data.clone();
+            fft(&mut actual);
+            ifft(&mut actual);
+            for (a, e) in actual.iter().zip(data.iter()) {
+                // a few ulps per butterfly stage
+                assert!((a - e).norm() < 16.0 * f32::EPSILON * (n as f32).log2(), "n = {}", n);
+            }
+        }
+    }
+
+#[test]
+    fn test_resynthesize(){
+        let sine: Sample = util::sinewaves(1.0, 1000, &[(5.0,90.0),(60.0,0.0)]);
+        let restored = resynthesize(&spectrum(&sine), sine.rate);
+        assert_eq!(restored.rate, sine.rate);
+        for (a, b) in restored.data.iter().zip(sine.data.iter()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+#[test]
+    fn test_czt_matches_dft(){
+        // full circle czt is the dft
+        let data: Vec<Complex<f32>> = complex_signal(100, 0);
+        let step = 2.0 * std::f64::consts::PI / 100.0;
+        let actual = czt(&data, 100, 0.0, step);
+        for (a, e) in actual.iter().zip(dft(&data).iter()) {
+            assert!((a - e).norm() < 1e-2);
+        }
+    }
+
+#[test]
+    fn test_zoom_band(){
+        // 49.9 hz tone resolved in a 49..51 hz band at 0.01 hz steps
+        let sine = Sample {
+            data: (0..16_000)
+                .map(|i| (2.0 * std::f64::consts::PI * 49.9 * i as f64 / 8000.0).sin() as f32)
+                .collect(),
+            rate: 8000,
+        };
+        let band = zoom(&sine, 49.0, 0.01, 201);
+        assert_eq!(band.len(), 201);
+        let peak = band.iter()
+            .enumerate()
+            .fold((0, 0.0), |acc, (k, c)| if c.norm() > acc.1 { (k, c.norm()) } else { acc });
+        assert_eq!(peak.0, 90);
+        assert!((peak.1 - 0.5).abs() < 1e-2);
+    }
+
+#[test]
+    fn test_spectrum_phasor(){
+        let sine: Sample = util::sinewaves(1.0, 1024, &[(5.0,90.0),(60.0,0.0)]);
+        let bins = spectrum(&sine);
+
+        let polar = bin_to_phasor(bins[5], sine.data.len()).to_polar();
+        assert!((polar.0 - 0.5).abs() < 1e-3);
+        assert_eq!((polar.1 * 180.0 / PI).round(), 90.0);
+
+        let polar = bin_to_phasor(bins[60], sine.data.len()).to_polar();
+        assert!((polar.0 - 0.5).abs() < 1e-3);
+        assert_eq!((polar.1 * 180.0 / PI).round(), 0.0);
+    }
+}
@@ -1,51 +1,57 @@
 // utility module
 
-use std::f32::consts::PI;
-use super::Sample;
+use std::f64::consts::PI;
+use super::{Sample, Phasor, Real};
 
 // generates sinewave
 // arguments:
 // f - frequency to generate
 // t - time in seconds
 // ss - stepsize in seconds
-pub fn sinewave(f: f32, p: f32, t: f32, sr: usize, a: f32) -> Sample {
+pub fn sinewave<T: Real>(f: T, p: T, t: T, sr: usize, a: T) -> Sample<T> {
     // precalculate 2πf
-    let fc = 2.0 * PI * f;
+    let fc = 2.0 * PI * f.as_f64();
 
     // precalculate phase in radians
-    let rad = p * PI / 180.0;
+    let rad = p.as_f64() * PI / 180.0;
 
     // sine generator
     // f(t) = amplitude
-    let mut data = vec![];
-    let dt = 1.0 / sr as f32;
-    let mut t0 = 0.0;
+    // the sample count is still that of stepping t0 by dt until t,
+    // but time is derived from the sample index in f64 instead of
+    // accumulated, so long signals do not drift
+    let step = T::one() / T::from_f64(sr as f64);
+    let mut len = 0;
+    let mut t0 = T::zero();
     while t0 < t {
-        let i = (fc * t0 + rad).sin() * a;
-        data.push(i);
-        t0 += dt;
+        len += 1;
+        t0 += step;
     }
+    let dt = 1.0 / sr as f64;
+    let data = (0..len)
+        .map(|i| T::from_f64((fc * i as f64 * dt + rad).sin()) * a)
+        .collect();
 
-    Sample{ data: data, rate: sr }
+    Sample{ data, rate: sr }
 }
 
 // generates sinewaves from list of (freq,phase) pairs
-pub fn sinewaves(t: f32, sr: usize, frequencies: &[(f32,f32)]) -> Sample {
+pub fn sinewaves<T: Real>(t: T, sr: usize, frequencies: &[(T,T)]) -> Sample<T> {
     // mix and generate samples
     Sample{
-        data: frequencies.into_iter()
+        data: frequencies.iter()
         .fold(vec![],|acc,fp| {
             println!("{} hz, {} phase", fp.0, fp.1);
-            if acc.len() > 0 {
+            if !acc.is_empty() {
                 acc.into_iter()
-                    .zip(sinewave(fp.0,fp.1,t,sr,1.0).data.into_iter())
+                    .zip(sinewave(fp.0,fp.1,t,sr,T::one()).data)
                     .map(|(t0,t1)|{
                         t0 + t1
                     })
                     .collect()
             }
             else {
-                sinewave(fp.0,fp.1,t,sr,1.0).data
+                sinewave(fp.0,fp.1,t,sr,T::one()).data
             }
         }),
         rate: sr
@@ -69,8 +75,8 @@ pub fn parse_freq_phase_pairs(fplist: Vec<String>) -> Vec<(f32,f32)> {
 }
 
 // adjusts peaks location + normalizes peaks
-pub fn adjust_peaks(phasors: &[super::Phasor], peaks: &[usize]) -> Vec<usize> {
-    let peaks_amp: Vec<(f32, usize)> = peaks.iter()
+pub fn adjust_peaks<T: Real>(phasors: &[Phasor<T>], peaks: &[usize]) -> Vec<usize> {
+    let peaks_amp: Vec<(T, usize)> = peaks.iter()
         .map(|&p0| {
             let p0_amp = phasors[p0].complex.to_polar().0;
             let mut p1 = p0 + 1;
@@ -83,10 +89,10 @@ pub fn adjust_peaks(phasors: &[super::Phasor], peaks: &[usize]) -> Vec<usize> {
     .collect();
 
     // get max amplitude
-    let p_max: f32 = peaks_amp.iter().fold(0.0, |acc, p| acc.max(p.0));
+    let p_max: T = peaks_amp.iter().fold(T::zero(), |acc, p| acc.max(p.0));
 
     let mut peaks_normalized: Vec<usize> = peaks_amp.iter()
-        .filter(|&p|{ p.0 > p_max * 0.3333333 })
+        .filter(|&p|{ p.0 > p_max * T::from_f64(0.3333333) })
         .map(|&p| p.1)
         .collect();
     peaks_normalized.dedup();
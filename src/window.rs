@@ -0,0 +1,241 @@
+// window functions applied before analysis
+// all windows are periodic (DFT-even), so a length N window
+// repeats seamlessly with period N
+
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+use super::{Sample, Real};
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum Window {
+    #[default]
+    Rectangular,
+    Hann,
+    Hamming,
+    Blackman,
+    // 4-term blackman-harris
+    BlackmanHarris,
+    FlatTop,
+    // shape parameter β
+    Kaiser(f32),
+    // tapered fraction α in 0.0..1.0
+    Tukey(f32),
+}
+
+// sum of cosines a0 - a1 cos(x) + a2 cos(2x) - ...
+fn cosine_sum(coefs: &[f64], x: f64) -> f64 {
+    coefs.iter()
+        .enumerate()
+        .fold(0.0, |acc, (k, &a)| {
+            let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+            acc + sign * a * (k as f64 * x).cos()
+        })
+}
+
+// zeroth order modified bessel function of the first kind
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-12 {
+        term *= (x / (2.0 * k)) * (x / (2.0 * k));
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+impl Window {
+    // window value at index n of len
+    pub(crate) fn value(&self, n: usize, len: usize) -> f64 {
+        // a single point has no taper, periodic windows would put it
+        // at their zero
+        if len <= 1 {
+            return 1.0;
+        }
+        let x = 2.0 * PI * n as f64 / len as f64;
+        match *self {
+            Window::Rectangular => 1.0,
+            Window::Hann => cosine_sum(&[0.5, 0.5], x),
+            Window::Hamming => cosine_sum(&[0.54, 0.46], x),
+            Window::Blackman => cosine_sum(&[0.42, 0.5, 0.08], x),
+            Window::BlackmanHarris => cosine_sum(&[0.35875, 0.48829, 0.14128, 0.01168], x),
+            Window::FlatTop => cosine_sum(&[0.215_578_95, 0.416_631_58, 0.277_263_158, 0.083_578_947, 0.006_947_368], x),
+            Window::Kaiser(beta) => {
+                let beta = beta as f64;
+                let r = 2.0 * n as f64 / len as f64 - 1.0;
+                bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) / bessel_i0(beta)
+            }
+            Window::Tukey(alpha) => {
+                let alpha = (alpha as f64).clamp(0.0, 1.0);
+                let taper = alpha * len as f64 / 2.0;
+                // distance from the nearest edge, periodic
+                let edge = (n as f64).min(len as f64 - n as f64);
+                if edge >= taper {
+                    1.0
+                } else {
+                    0.5 * (1.0 - (PI * edge / taper).cos())
+                }
+            }
+        }
+    }
+
+    // window coefficients for len points
+    pub fn coefficients<T: Real>(&self, len: usize) -> Vec<T> {
+        (0..len).map(|n| T::from_f64(self.value(n, len))).collect()
+    }
+
+    // mean of the window, the amplitude a windowed tone is scaled by
+    pub fn coherent_gain(&self, len: usize) -> f64 {
+        if len == 0 {
+            return 1.0;
+        }
+        let sum: f64 = (0..len).map(|n| self.value(n, len)).sum();
+        sum / len as f64
+    }
+
+    // equivalent noise bandwidth in bins
+    // N * sum(w²) / sum(w)²
+    pub fn enbw(&self, len: usize) -> f64 {
+        if len == 0 {
+            return 1.0;
+        }
+        let (sum, sum_sq) = (0..len)
+            .map(|n| self.value(n, len))
+            .fold((0.0, 0.0), |acc, w| (acc.0 + w, acc.1 + w * w));
+        len as f64 * sum_sq / (sum * sum)
+    }
+
+    // multiplies data by the window in place
+    pub fn apply<T: Real>(&self, data: &mut [T]) {
+        if *self == Window::Rectangular {
+            return;
+        }
+        let len = data.len();
+        for (n, a) in data.iter_mut().enumerate() {
+            *a *= T::from_f64(self.value(n, len));
+        }
+    }
+
+    // windowed copy of sample, divided by coherent gain so
+    // that tone amplitudes are preserved
+    pub fn apply_sample<T: Real>(&self, sample: &Sample<T>) -> Sample<T> {
+        let mut data = sample.data.clone();
+        self.apply(&mut data);
+        let gain = self.coherent_gain(data.len());
+        if gain != 1.0 {
+            let gain = T::from_f64(gain);
+            for a in data.iter_mut() {
+                *a /= gain;
+            }
+        }
+        Sample{ data, rate: sample.rate }
+    }
+}
+
+// parses "name" or "name:parameter", eg. "hann", "kaiser:8.6"
+impl FromStr for Window {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Window, String> {
+        let components: Vec<&str> = s.split(':').collect();
+        let param = |default: f32| -> Result<f32, String> {
+            match components.get(1) {
+                Some(p) => p.parse::<f32>().map_err(|_| format!("invalid window parameter '{}'", p)),
+                None => Ok(default),
+            }
+        };
+        match components[0].to_lowercase().as_str() {
+            "rect" | "rectangular" | "none" => Ok(Window::Rectangular),
+            "hann" | "hanning" => Ok(Window::Hann),
+            "hamming" => Ok(Window::Hamming),
+            "blackman" => Ok(Window::Blackman),
+            "blackman-harris" | "blackmanharris" => Ok(Window::BlackmanHarris),
+            "flattop" | "flat-top" => Ok(Window::FlatTop),
+            "kaiser" => param(8.6).map(Window::Kaiser),
+            "tukey" => param(0.5).map(Window::Tukey),
+            name => Err(format!("unknown window '{}'", name)),
+        }
+    }
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Window::Rectangular => write!(f, "rectangular"),
+            Window::Hann => write!(f, "hann"),
+            Window::Hamming => write!(f, "hamming"),
+            Window::Blackman => write!(f, "blackman"),
+            Window::BlackmanHarris => write!(f, "blackman-harris"),
+            Window::FlatTop => write!(f, "flattop"),
+            Window::Kaiser(beta) => write!(f, "kaiser:{}", beta),
+            Window::Tukey(alpha) => write!(f, "tukey:{}", alpha),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+use super::*;
+
+#[test]
+    fn test_window_gains(){
+        let len = 4096;
+        let expected = [
+            (Window::Rectangular, 1.0, 1.0),
+            (Window::Hann, 0.5, 1.5),
+            (Window::Hamming, 0.54, 1.3628),
+            (Window::Blackman, 0.42, 1.7268),
+            (Window::BlackmanHarris, 0.35875, 2.0044),
+            (Window::FlatTop, 0.2156, 3.7702),
+        ];
+        for &(w, cg, enbw) in expected.iter() {
+            assert!((w.coherent_gain(len) - cg).abs() < 1e-3, "{} cg {}", w, w.coherent_gain(len));
+            assert!((w.enbw(len) - enbw).abs() < 1e-3, "{} enbw {}", w, w.enbw(len));
+        }
+    }
+
+#[test]
+    fn test_window_limits(){
+        // tukey spans rectangular to hann, kaiser with β = 0 is rectangular
+        let len = 64;
+        let hann: Vec<f32> = Window::Hann.coefficients(len);
+        for (a, b) in Window::Tukey(1.0).coefficients::<f32>(len).iter().zip(hann.iter()) {
+            assert!((a - b).abs() < 1e-6);
+        }
+        assert!(Window::Tukey(0.0).coefficients::<f32>(len).iter().all(|&w| w == 1.0));
+        assert!(Window::Kaiser(0.0).coefficients::<f32>(len).iter().all(|&w| (w - 1.0).abs() < 1e-6));
+        assert!(Window::Kaiser(8.6).enbw(len) > Window::Kaiser(2.0).enbw(len));
+    }
+
+#[test]
+    fn test_window_single_point(){
+        // one point windows act as rectangular instead of dividing by 0
+        let windows = [
+            Window::Rectangular, Window::Hann, Window::Hamming, Window::Blackman,
+            Window::BlackmanHarris, Window::FlatTop, Window::Kaiser(8.6), Window::Tukey(0.5),
+        ];
+        let sample = Sample{ data: vec![0.75f32], rate: 1000 };
+        for w in windows.iter() {
+            assert_eq!(w.coefficients::<f32>(1), vec![1.0], "{}", w);
+            assert_eq!(w.coherent_gain(1), 1.0, "{}", w);
+            assert_eq!(w.enbw(1), 1.0, "{}", w);
+            assert_eq!(w.enbw(0), 1.0, "{}", w);
+            assert_eq!(w.apply_sample(&sample).data, vec![0.75], "{}", w);
+        }
+    }
+
+#[test]
+    fn test_window_parse(){
+        assert_eq!("hann".parse::<Window>(), Ok(Window::Hann));
+        assert_eq!("Blackman-Harris".parse::<Window>(), Ok(Window::BlackmanHarris));
+        assert_eq!("kaiser:5".parse::<Window>(), Ok(Window::Kaiser(5.0)));
+        assert_eq!("tukey".parse::<Window>(), Ok(Window::Tukey(0.5)));
+        assert!("triangle".parse::<Window>().is_err());
+        assert!("kaiser:x".parse::<Window>().is_err());
+        for w in &[Window::FlatTop, Window::Kaiser(3.5), Window::Tukey(0.25)] {
+            assert_eq!(w.to_string().parse::<Window>().as_ref(), Ok(w));
+        }
+    }
+}